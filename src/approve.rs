@@ -0,0 +1,98 @@
+use crate::error::X402Error;
+use crate::payment::PaymentRequired;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// The JSON document passed to the approval command: the payment
+/// requirements as in `--x402-json` output, plus the URL being paid for.
+fn request_json(url: &str, required: &PaymentRequired) -> serde_json::Value {
+    let mut json = required.to_json();
+    json["url"] = serde_json::Value::String(url.to_string());
+    json
+}
+
+/// Split the approval command into a program and its arguments. Quoting
+/// works as in a shell, but no shell runs, so nothing is expanded.
+fn program(command: &str) -> Result<Command, X402Error> {
+    let words = shell_words::split(command)
+        .map_err(|e| X402Error::Config(format!("Invalid approval command '{}': {}", command, e)))?;
+    let (program, args) = words
+        .split_first()
+        .ok_or_else(|| X402Error::Config("Approval command is empty".to_string()))?;
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    Ok(cmd)
+}
+
+/// Ask `command` whether to pay: the program runs with the payment
+/// requirements as JSON on stdin, and approves by exiting 0. Its output goes
+/// to stderr so it can't mix with the response body, or nowhere when `json`
+/// is set, as stderr then carries only the JSON report.
+pub fn approve(
+    command: &str,
+    url: &str,
+    required: &PaymentRequired,
+    json: bool,
+) -> Result<(), X402Error> {
+    let failed = |e: io::Error| {
+        X402Error::Config(format!(
+            "Failed to run approval command '{}': {}",
+            command, e
+        ))
+    };
+
+    let mut cmd = program(command)?;
+    cmd.stdin(Stdio::piped());
+    if json {
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    } else {
+        cmd.stdout(io::stderr());
+    }
+    let mut child = cmd.spawn().map_err(failed)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may exit without reading its input; that's its answer
+        let _ = stdin.write_all(request_json(url, required).to_string().as_bytes());
+    }
+    let status = child.wait().map_err(failed)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(X402Error::Payment(format!(
+            "payment declined by approval command ({})",
+            status
+        )))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::payment::fixtures::{v1_required, USDC};
+
+    const URL: &str = "http://localhost/paid";
+
+    #[test]
+    fn test_exit_status_decides() {
+        let required = v1_required("10000", USDC);
+        assert!(approve("true", URL, &required, false).is_ok());
+        assert!(approve("false", URL, &required, false).is_err());
+    }
+
+    #[test]
+    fn test_requirements_on_stdin() {
+        let required = v1_required("10000", USDC);
+        let approve_base_sepolia = r#"grep -q '"network":"base-sepolia"'"#;
+        assert!(approve(approve_base_sepolia, URL, &required, false).is_ok());
+        let approve_base = r#"grep -q '"network":"base"'"#;
+        assert!(approve(approve_base, URL, &required, false).is_err());
+    }
+
+    #[test]
+    fn test_no_shell() {
+        let required = v1_required("10000", USDC);
+        // `true;` is not a program, and `false` is just its argument
+        assert!(approve("true; false", URL, &required, false).is_err());
+        assert!(approve("", URL, &required, false).is_err());
+    }
+}
@@ -1,36 +1,16 @@
-use alloy_signer_local::PrivateKeySigner;
+use alloy_primitives::Address;
 
+use crate::chains::{self, Chain, SolanaChain, CHAINS};
 use crate::error::X402Error;
+use std::time::Duration;
 
 const DEFAULT_RPC_URL: &str = "https://mainnet.base.org";
 const BALANCE_OF_SELECTOR: &str = "0x70a08231";
 const DECIMALS_SELECTOR: &str = "0x313ce567";
 const SYMBOL_SELECTOR: &str = "0x95d89b41";
-
-/// USDC contract addresses by chain ID.
-fn usdc_contract(chain_id: u64) -> Result<&'static str, X402Error> {
-    match chain_id {
-        8453 => Ok("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
-        84532 => Ok("0x036CbD53842c5426634e7929541eC2318f3dCF7e"),
-        _ => Err(X402Error::Rpc(format!(
-            "Unsupported chain ID: {}. Only Base (8453) and Base Sepolia (84532) are supported. Use --x402-token to specify a token address.",
-            chain_id
-        ))),
-    }
-}
-
-fn chain_name(chain_id: u64) -> &'static str {
-    match chain_id {
-        1 => "Ethereum",
-        8453 => "Base",
-        10 => "Optimism",
-        42161 => "Arbitrum",
-        137 => "Polygon",
-        84532 => "Base Sepolia",
-        11155111 => "Sepolia",
-        _ => "Unknown",
-    }
-}
+const NATIVE_DECIMALS: u8 = 18;
+/// Per-request timeout for `--all-networks`, so one slow RPC can't stall the table
+const ALL_NETWORKS_TIMEOUT: Duration = Duration::from_secs(10);
 
 fn parse_hex_u128(hex: &str) -> Result<u128, X402Error> {
     let hex = hex.trim().trim_start_matches("0x").trim_start_matches("0X");
@@ -48,17 +28,51 @@ fn format_token_balance(raw: u128, decimals: u8) -> String {
     format!("{}.{:0>width$}", whole, frac, width = decimals as usize)
 }
 
-/// Resolve RPC URL: CLI flag > env var > default.
-pub fn resolve_rpc(cli_rpc_url: Option<&str>) -> String {
+/// Parse a human-readable token amount (e.g. "1.50") into atomic units.
+/// Digits beyond the token's precision are truncated.
+pub fn parse_token_amount(amount: &str, decimals: u8) -> Result<u128, X402Error> {
+    let invalid = || X402Error::General(format!("Invalid token amount: {}", amount));
+    let amount = amount.trim();
+    let (whole, frac) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && frac.is_empty() {
+        return Err(invalid());
+    }
+    if !whole
+        .chars()
+        .chain(frac.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let frac: String = frac.chars().take(decimals as usize).collect();
+    let digits = format!("{}{:0<width$}", whole, frac, width = decimals as usize);
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits.parse::<u128>().map_err(|_| invalid())
+}
+
+/// Resolve RPC URL: CLI flag > env var > network's public RPC > default.
+pub fn resolve_rpc(cli_rpc_url: Option<&str>, network: Option<&str>) -> Result<String, X402Error> {
     if let Some(url) = cli_rpc_url {
-        return url.to_string();
+        return Ok(url.to_string());
     }
     if let Ok(url) = std::env::var("X402_RPC_URL") {
         if !url.is_empty() {
-            return url;
+            return Ok(url);
         }
     }
-    DEFAULT_RPC_URL.to_string()
+    if let Some(network) = network {
+        let chain = chains::by_network(network).ok_or_else(|| {
+            X402Error::General(format!(
+                "Unknown network: {}. Use --x402-rpc-url to query other chains.",
+                network
+            ))
+        })?;
+        return Ok(chain.rpc_url.to_string());
+    }
+    Ok(DEFAULT_RPC_URL.to_string())
 }
 
 /// Make an eth_call and return the hex result string.
@@ -95,6 +109,126 @@ async fn eth_call(
         .ok_or_else(|| X402Error::Rpc("Missing result in RPC response".to_string()))
 }
 
+/// Query an ERC-20 `balanceOf` for `address`, in atomic units.
+pub async fn token_balance(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    token_address: &str,
+    address: &str,
+) -> Result<u128, X402Error> {
+    // Selector + 32-byte left-padded address
+    let padded_address = format!("{:0>64}", address.trim_start_matches("0x"));
+    let calldata = format!("{}{}", BALANCE_OF_SELECTOR, padded_address);
+    let hex = eth_call(client, rpc_url, token_address, &calldata).await?;
+    // A call to an address without code returns no data rather than zero
+    if hex.trim().trim_start_matches("0x").is_empty() {
+        return Err(X402Error::Rpc(format!(
+            "No token contract at {}",
+            token_address
+        )));
+    }
+    parse_hex_u128(&hex)
+}
+
+/// Query the native gas token balance via `eth_getBalance`.
+async fn eth_get_balance(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    address: &str,
+) -> Result<u128, X402Error> {
+    let response = client
+        .post(rpc_url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "eth_getBalance",
+            "params": [address, "latest"],
+            "id": 1
+        }))
+        .send()
+        .await
+        .map_err(|e| X402Error::Rpc(format!("Failed to connect to RPC: {}", e)))?;
+
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| X402Error::Rpc(format!("Invalid RPC response: {}", e)))?;
+
+    if let Some(err) = json.get("error") {
+        return Err(X402Error::Rpc(format!("RPC error: {}", err)));
+    }
+
+    let hex = json["result"]
+        .as_str()
+        .ok_or_else(|| X402Error::Rpc("Missing result in RPC response".to_string()))?;
+    parse_hex_u128(hex)
+}
+
+/// Make a Solana JSON-RPC request and return its `result`.
+async fn solana_rpc(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, X402Error> {
+    let response = client
+        .post(rpc_url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1
+        }))
+        .send()
+        .await
+        .map_err(|e| X402Error::Rpc(format!("Failed to connect to RPC: {}", e)))?;
+
+    let mut json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| X402Error::Rpc(format!("Invalid RPC response: {}", e)))?;
+
+    if let Some(err) = json.get("error") {
+        return Err(X402Error::Rpc(format!("RPC error: {}", err)));
+    }
+
+    match json.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(X402Error::Rpc("Missing result in RPC response".to_string())),
+    }
+}
+
+/// Sum the token amounts of a `getTokenAccountsByOwner` (jsonParsed) result.
+/// An owner without token accounts has a balance of zero.
+fn sum_token_accounts(result: &serde_json::Value) -> Result<u128, X402Error> {
+    let accounts = result["value"]
+        .as_array()
+        .ok_or_else(|| X402Error::Rpc("Missing token accounts in RPC response".to_string()))?;
+    accounts.iter().try_fold(0u128, |total, account| {
+        let amount = account["account"]["data"]["parsed"]["info"]["tokenAmount"]["amount"]
+            .as_str()
+            .and_then(|a| a.parse::<u128>().ok())
+            .ok_or_else(|| X402Error::Rpc("Invalid token account in RPC response".to_string()))?;
+        Ok(total.saturating_add(amount))
+    })
+}
+
+/// Query the SPL token balance of `mint` held by `owner`, in atomic units.
+pub async fn spl_token_balance(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    mint: &str,
+    owner: &str,
+) -> Result<u128, X402Error> {
+    let result = solana_rpc(
+        client,
+        rpc_url,
+        "getTokenAccountsByOwner",
+        serde_json::json!([owner, {"mint": mint}, {"encoding": "jsonParsed"}]),
+    )
+    .await?;
+    sum_token_accounts(&result)
+}
+
 /// Query the ERC-20 `decimals()` view function. Falls back to 18 on failure.
 async fn query_token_decimals(client: &reqwest::Client, rpc_url: &str, token_address: &str) -> u8 {
     match eth_call(client, rpc_url, token_address, DECIMALS_SELECTOR).await {
@@ -141,16 +275,161 @@ fn decode_abi_string(hex: &str) -> Option<String> {
     String::from_utf8(bytes).ok()
 }
 
-/// Query and display the wallet's token balance on the detected chain.
-pub async fn query_balance(
-    signer: &PrivateKeySigner,
-    rpc_url: &str,
-    token_override: Option<&str>,
-) -> Result<(), X402Error> {
-    let client = reqwest::Client::new();
+/// Result of a balance query.
+#[derive(Debug, serde::Serialize)]
+pub struct BalanceReport {
+    pub network: String,
+    /// EVM chain ID; absent on Solana
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    pub rpc_url: String,
+    pub address: String,
+    pub token: String,
+    pub symbol: String,
+    pub decimals: u8,
+    /// Balance in atomic units
+    pub raw: String,
+    /// Balance in token units
+    pub balance: String,
+}
 
-    // 1. Detect chain ID
-    let chain_id_response = client
+impl BalanceReport {
+    /// Print to stderr (matches existing diagnostic output convention)
+    pub fn print(&self) {
+        match self.chain_id {
+            Some(chain_id) => eprintln!("Network:  {} (Chain ID: {})", self.network, chain_id),
+            None => eprintln!("Network:  {}", self.network),
+        }
+        eprintln!("RPC:      {}", self.rpc_url);
+        eprintln!("Address:  {}", self.address);
+        eprintln!("{}:     {} {}", self.symbol, self.balance, self.symbol);
+    }
+}
+
+/// USDC and native balances on one network, or why they couldn't be fetched.
+#[derive(Debug, serde::Serialize)]
+pub struct NetworkBalance {
+    pub network: String,
+    pub chain_id: u64,
+    /// USDC balance in token units
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usdc: Option<String>,
+    /// Native gas token balance in token units
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native: Option<String>,
+    pub native_symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of `--x402-balance --all-networks`.
+#[derive(Debug, serde::Serialize)]
+pub struct BalanceTable {
+    pub address: String,
+    pub networks: Vec<NetworkBalance>,
+}
+
+impl NetworkBalance {
+    fn row(&self) -> String {
+        match self.error {
+            Some(ref error) => {
+                format!(
+                    "{:<18}  {:<9}  error: {}",
+                    self.network, self.chain_id, error
+                )
+            }
+            None => format!(
+                "{:<18}  {:<9}  {:<16}  {} {}",
+                self.network,
+                self.chain_id,
+                self.usdc.as_deref().unwrap_or("-"),
+                self.native.as_deref().unwrap_or("-"),
+                self.native_symbol
+            ),
+        }
+    }
+}
+
+impl BalanceTable {
+    /// Whether no network could be queried at all.
+    pub fn all_failed(&self) -> bool {
+        self.networks.iter().all(|n| n.error.is_some())
+    }
+
+    /// Print to stderr (matches existing diagnostic output convention)
+    pub fn print(&self) {
+        eprintln!("Address:  {}", self.address);
+        eprintln!();
+        eprintln!(
+            "{:<18}  {:<9}  {:<16}  NATIVE",
+            "NETWORK", "CHAIN ID", "USDC"
+        );
+        for network in &self.networks {
+            eprintln!("{}", network.row());
+        }
+    }
+}
+
+async fn query_network(
+    client: reqwest::Client,
+    chain: &'static Chain,
+    address: String,
+) -> NetworkBalance {
+    let (usdc, native) = tokio::join!(
+        token_balance(&client, chain.rpc_url, chain.usdc, &address),
+        eth_get_balance(&client, chain.rpc_url, &address),
+    );
+
+    let mut balance = NetworkBalance {
+        network: chain.name.to_string(),
+        chain_id: chain.id,
+        usdc: None,
+        native: None,
+        native_symbol: chain.native_symbol.to_string(),
+        error: None,
+    };
+    match usdc.and_then(|usdc| Ok((usdc, native?))) {
+        Ok((usdc, native)) => {
+            balance.usdc = Some(format_token_balance(usdc, chain.usdc_decimals));
+            balance.native = Some(format_token_balance(native, NATIVE_DECIMALS));
+        }
+        Err(e) => balance.error = Some(e.to_string()),
+    }
+    balance
+}
+
+/// Query USDC and native balances on every known network concurrently.
+/// A failing RPC is reported in its row instead of failing the whole query.
+pub async fn query_all_balances(address: Address) -> Result<BalanceTable, X402Error> {
+    let client = reqwest::Client::builder()
+        .timeout(ALL_NETWORKS_TIMEOUT)
+        .build()
+        .map_err(|e| X402Error::Rpc(format!("Failed to create HTTP client: {}", e)))?;
+    let address = format!("{:?}", address);
+
+    let tasks: Vec<_> = CHAINS
+        .iter()
+        .map(|chain| tokio::spawn(query_network(client.clone(), chain, address.clone())))
+        .collect();
+
+    let mut networks = Vec::with_capacity(tasks.len());
+    for (task, chain) in tasks.into_iter().zip(CHAINS) {
+        networks.push(task.await.unwrap_or_else(|e| NetworkBalance {
+            network: chain.name.to_string(),
+            chain_id: chain.id,
+            usdc: None,
+            native: None,
+            native_symbol: chain.native_symbol.to_string(),
+            error: Some(e.to_string()),
+        }));
+    }
+
+    Ok(BalanceTable { address, networks })
+}
+
+/// Chain ID of the network an RPC endpoint serves.
+pub async fn chain_id(client: &reqwest::Client, rpc_url: &str) -> Result<u64, X402Error> {
+    let response = client
         .post(rpc_url)
         .json(&serde_json::json!({
             "jsonrpc": "2.0",
@@ -162,20 +441,31 @@ pub async fn query_balance(
         .await
         .map_err(|e| X402Error::Rpc(format!("Failed to connect to RPC: {}", e)))?;
 
-    let chain_id_json: serde_json::Value = chain_id_response
+    let json: serde_json::Value = response
         .json()
         .await
         .map_err(|e| X402Error::Rpc(format!("Invalid RPC response: {}", e)))?;
 
-    if let Some(err) = chain_id_json.get("error") {
+    if let Some(err) = json.get("error") {
         return Err(X402Error::Rpc(format!("RPC error: {}", err)));
     }
 
-    let chain_id_hex = chain_id_json["result"]
+    let chain_id_hex = json["result"]
         .as_str()
         .ok_or_else(|| X402Error::Rpc("Missing chain ID in RPC response".to_string()))?;
+    Ok(parse_hex_u128(chain_id_hex)? as u64)
+}
+
+/// Query the wallet's token balance on the detected chain.
+pub async fn query_balance(
+    address: Address,
+    rpc_url: &str,
+    token_override: Option<&str>,
+) -> Result<BalanceReport, X402Error> {
+    let client = reqwest::Client::new();
 
-    let chain_id = parse_hex_u128(chain_id_hex)? as u64;
+    // 1. Detect chain ID
+    let chain_id = chain_id(&client, rpc_url).await?;
 
     // 2. Resolve token contract, decimals, and symbol
     let (token_address, decimals, symbol) = if let Some(addr) = token_override {
@@ -185,31 +475,58 @@ pub async fn query_balance(
         (addr.to_string(), decimals, symbol)
     } else {
         // Default: USDC lookup (errors on unsupported chains)
-        let addr = usdc_contract(chain_id)?;
-        (addr.to_string(), 6u8, "USDC".to_string())
+        let chain = chains::by_id(chain_id).ok_or_else(|| {
+            X402Error::Rpc(format!(
+                "Unsupported chain ID: {}. Use --x402-token to specify a token address.",
+                chain_id
+            ))
+        })?;
+        (
+            chain.usdc.to_string(),
+            chain.usdc_decimals,
+            "USDC".to_string(),
+        )
     };
 
-    // 3. Build balanceOf calldata: selector + zero-padded address
-    let address = format!("{:?}", signer.address()); // 0x-prefixed
-    let padded_address = format!("{:0>64}", &address[2..]); // 32-byte left-padded
-    let calldata = format!("{}{}", BALANCE_OF_SELECTOR, padded_address);
-
-    // 4. Query balance via eth_call
-    let balance_hex = eth_call(&client, rpc_url, &token_address, &calldata).await?;
-    let balance_raw = parse_hex_u128(&balance_hex)?;
-    let balance_formatted = format_token_balance(balance_raw, decimals);
-
-    // 5. Print to stderr (matches existing diagnostic output convention)
-    eprintln!(
-        "Network:  {} (Chain ID: {})",
-        chain_name(chain_id),
-        chain_id
-    );
-    eprintln!("RPC:      {}", rpc_url);
-    eprintln!("Address:  {:?}", signer.address());
-    eprintln!("{}:     {} {}", symbol, balance_formatted, symbol);
+    // 3. Query balanceOf via eth_call
+    let address = format!("{:?}", address); // 0x-prefixed
+    let balance_raw = token_balance(&client, rpc_url, &token_address, &address).await?;
+
+    Ok(BalanceReport {
+        network: chains::by_id(chain_id)
+            .map_or("Unknown", |c| c.name)
+            .to_string(),
+        chain_id: Some(chain_id),
+        rpc_url: rpc_url.to_string(),
+        address,
+        token: token_address,
+        symbol,
+        decimals,
+        raw: balance_raw.to_string(),
+        balance: format_token_balance(balance_raw, decimals),
+    })
+}
 
-    Ok(())
+/// Query the USDC balance of a Solana wallet.
+pub async fn query_solana_balance(
+    owner: &str,
+    chain: &SolanaChain,
+    rpc_url: &str,
+) -> Result<BalanceReport, X402Error> {
+    let client = reqwest::Client::new();
+    let balance_raw = spl_token_balance(&client, rpc_url, chain.usdc, owner).await?;
+
+    Ok(BalanceReport {
+        network: chain.name.to_string(),
+        chain_id: None,
+        rpc_url: rpc_url.to_string(),
+        address: owner.to_string(),
+        token: chain.usdc.to_string(),
+        symbol: "USDC".to_string(),
+        decimals: chain.usdc_decimals,
+        raw: balance_raw.to_string(),
+        balance: format_token_balance(balance_raw, chain.usdc_decimals),
+    })
 }
 
 #[cfg(test)]
@@ -277,32 +594,89 @@ mod tests {
     }
 
     #[test]
-    fn test_usdc_contract_base_mainnet() {
+    fn test_parse_token_amount_usdc() {
+        assert_eq!(parse_token_amount("1", 6).unwrap(), 1_000_000);
+        assert_eq!(parse_token_amount("0.01", 6).unwrap(), 10_000);
+        assert_eq!(parse_token_amount("1.5", 6).unwrap(), 1_500_000);
+        assert_eq!(parse_token_amount(".5", 6).unwrap(), 500_000);
+    }
+
+    #[test]
+    fn test_parse_token_amount_truncates_precision() {
+        assert_eq!(parse_token_amount("0.0000019", 6).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_parse_token_amount_roundtrip() {
         assert_eq!(
-            usdc_contract(8453).unwrap(),
-            "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+            parse_token_amount(&format_token_balance(123, 6), 6).unwrap(),
+            123
         );
     }
 
     #[test]
-    fn test_usdc_contract_base_sepolia() {
+    fn test_parse_token_amount_invalid() {
+        assert!(parse_token_amount("", 6).is_err());
+        assert!(parse_token_amount(".", 6).is_err());
+        assert!(parse_token_amount("-1", 6).is_err());
+        assert!(parse_token_amount("1.2.3", 6).is_err());
+        assert!(parse_token_amount("$1", 6).is_err());
+    }
+
+    #[test]
+    fn test_resolve_rpc_network() {
         assert_eq!(
-            usdc_contract(84532).unwrap(),
-            "0x036CbD53842c5426634e7929541eC2318f3dCF7e"
+            resolve_rpc(Some("http://localhost:8545"), Some("polygon")).unwrap(),
+            "http://localhost:8545"
         );
+        assert!(resolve_rpc(None, Some("dogechain")).is_err());
     }
 
     #[test]
-    fn test_usdc_contract_unsupported() {
-        assert!(usdc_contract(1).is_err());
+    fn test_network_balance_row() {
+        let ok = NetworkBalance {
+            network: "Polygon".to_string(),
+            chain_id: 137,
+            usdc: Some("1.500000".to_string()),
+            native: Some("0.250000000000000000".to_string()),
+            native_symbol: "POL".to_string(),
+            error: None,
+        };
+        assert_eq!(
+            ok.row(),
+            "Polygon             137        1.500000          0.250000000000000000 POL"
+        );
+
+        let failed = NetworkBalance {
+            usdc: None,
+            native: None,
+            error: Some("RPC error: timeout".to_string()),
+            ..ok
+        };
+        assert_eq!(
+            failed.row(),
+            "Polygon             137        error: RPC error: timeout"
+        );
     }
 
     #[test]
-    fn test_chain_name() {
-        assert_eq!(chain_name(8453), "Base");
-        assert_eq!(chain_name(84532), "Base Sepolia");
-        assert_eq!(chain_name(1), "Ethereum");
-        assert_eq!(chain_name(99999), "Unknown");
+    fn test_sum_token_accounts() {
+        let account = |amount: &str| {
+            serde_json::json!({
+                "pubkey": "9C6hybhQ6Aycep9jaUnP6uL9ZYvDjUp1aSkFWPUFJtpj",
+                "account": {"data": {"parsed": {"info": {
+                    "mint": "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+                    "tokenAmount": {"amount": amount, "decimals": 6}
+                }}}}
+            })
+        };
+        let result = serde_json::json!({"value": [account("1500000"), account("2500")]});
+        assert_eq!(sum_token_accounts(&result).unwrap(), 1_502_500);
+        assert_eq!(
+            sum_token_accounts(&serde_json::json!({"value": []})).unwrap(),
+            0
+        );
+        assert!(sum_token_accounts(&serde_json::json!({})).is_err());
     }
 
     #[test]
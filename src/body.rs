@@ -0,0 +1,223 @@
+use crate::error::X402Error;
+use http::Extensions;
+use reqwest::header::{HeaderValue, CONTENT_LENGTH};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tokio_util::io::ReaderStream;
+
+/// Where a request body comes from.
+pub enum BodySource {
+    /// Data given on the command line
+    Bytes(Vec<u8>),
+    /// A file, streamed as the request is sent
+    File(FileSource),
+    /// Multipart form fields; files are streamed
+    Form(Vec<FormField>),
+}
+
+impl BodySource {
+    /// Whether any of the body was read from stdin.
+    pub fn reads_stdin(&self) -> bool {
+        match self {
+            BodySource::Bytes(_) => false,
+            BodySource::File(file) => file.is_stdin(),
+            BodySource::Form(fields) => fields.iter().any(|field| match field {
+                FormField::Text { .. } => false,
+                FormField::File { file, .. } => file.is_stdin(),
+            }),
+        }
+    }
+}
+
+pub enum FormField {
+    Text { name: String, value: String },
+    File { name: String, file: FileSource },
+}
+
+/// A file to upload, named on the command line or `-` for stdin.
+pub enum FileSource {
+    Path(PathBuf),
+    /// Stdin, copied to a temporary file: the request is sent again with a
+    /// payment after a 402, and stdin can only be read once.
+    Spooled(NamedTempFile),
+}
+
+impl FileSource {
+    pub fn open(arg: &str) -> Result<Self, X402Error> {
+        if arg == "-" {
+            let mut spool = NamedTempFile::new().map_err(|e| {
+                X402Error::General(format!("Failed to create temporary file: {}", e))
+            })?;
+            io::copy(&mut io::stdin().lock(), &mut spool)
+                .map_err(|e| X402Error::General(format!("Failed to read stdin: {}", e)))?;
+            return Ok(FileSource::Spooled(spool));
+        }
+        // Fail before connecting if the file can't be read
+        fs::File::open(arg)
+            .map_err(|e| X402Error::General(format!("Failed to read file {}: {}", arg, e)))?;
+        Ok(FileSource::Path(PathBuf::from(arg)))
+    }
+
+    fn is_stdin(&self) -> bool {
+        matches!(self, FileSource::Spooled(_))
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            FileSource::Path(path) => path,
+            FileSource::Spooled(spool) => spool.path(),
+        }
+    }
+
+    /// Name sent for the file in a multipart form.
+    pub fn file_name(&self) -> Option<&str> {
+        match self {
+            FileSource::Path(path) => path.file_name().and_then(|n| n.to_str()),
+            FileSource::Spooled(_) => None,
+        }
+    }
+
+    /// A fresh stream of the file's contents, and its length.
+    async fn stream(&self) -> Result<(Body, u64), X402Error> {
+        let read_error = |e: io::Error| {
+            X402Error::General(format!(
+                "Failed to read file {}: {}",
+                self.path().display(),
+                e
+            ))
+        };
+        let file = tokio::fs::File::open(self.path())
+            .await
+            .map_err(read_error)?;
+        let len = file.metadata().await.map_err(read_error)?.len();
+        Ok((Body::wrap_stream(ReaderStream::new(file)), len))
+    }
+}
+
+/// Middleware that gives each attempt at the request its own copy of the
+/// body.
+///
+/// A streamed body can only be sent once, yet the x402 client sends the
+/// request a second time with the payment attached. So the request is built
+/// without a body, and this middleware, registered innermost, reads the
+/// source afresh every time the request goes out.
+pub struct StreamBody {
+    source: BodySource,
+    /// Only used to encode multipart forms, which reqwest exposes through
+    /// its request builder alone; never sends anything
+    encoder: reqwest::Client,
+}
+
+impl StreamBody {
+    pub fn new(source: BodySource) -> Self {
+        StreamBody {
+            source,
+            encoder: reqwest::Client::new(),
+        }
+    }
+
+    async fn attach(&self, req: &mut Request) -> Result<(), X402Error> {
+        match &self.source {
+            BodySource::Bytes(bytes) => {
+                *req.body_mut() = Some(Body::from(bytes.clone()));
+            }
+            BodySource::File(file) => {
+                let (body, len) = file.stream().await?;
+                req.headers_mut()
+                    .insert(CONTENT_LENGTH, HeaderValue::from(len));
+                *req.body_mut() = Some(body);
+            }
+            BodySource::Form(fields) => {
+                let mut form = Form::new();
+                for field in fields {
+                    form = match field {
+                        FormField::Text { name, value } => form.text(name.clone(), value.clone()),
+                        FormField::File { name, file } => {
+                            let (body, len) = file.stream().await?;
+                            let part = Part::stream_with_length(body, len)
+                                .file_name(file.file_name().unwrap_or("file").to_string());
+                            form.part(name.clone(), part)
+                        }
+                    };
+                }
+                let mut encoded = self
+                    .encoder
+                    .request(req.method().clone(), req.url().clone())
+                    .multipart(form)
+                    .build()?;
+                // Content-Type with the boundary, and Content-Length
+                for (name, value) in encoded.headers() {
+                    req.headers_mut().insert(name.clone(), value.clone());
+                }
+                *req.body_mut() = encoded.body_mut().take();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for StreamBody {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        self.attach(&mut req)
+            .await
+            .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn test_file_streamed_on_every_attempt() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"payload").unwrap();
+        let source = FileSource::open(file.path().to_str().unwrap()).unwrap();
+        let stream_body = StreamBody::new(BodySource::File(source));
+
+        for _ in 0..2 {
+            let mut req = Request::new(
+                reqwest::Method::PUT,
+                "http://localhost/upload".parse().unwrap(),
+            );
+            stream_body.attach(&mut req).await.unwrap();
+            assert_eq!(req.headers()[CONTENT_LENGTH], "7");
+            // Streamed bodies can't be cloned, which is why each attempt
+            // gets its own
+            assert!(req.body().unwrap().as_bytes().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_form_sets_boundary() {
+        let stream_body = StreamBody::new(BodySource::Form(vec![FormField::Text {
+            name: "a".to_string(),
+            value: "b".to_string(),
+        }]));
+        let mut req = Request::new(
+            reqwest::Method::POST,
+            "http://localhost/form".parse().unwrap(),
+        );
+        stream_body.attach(&mut req).await.unwrap();
+        let content_type = req.headers()["content-type"].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert!(FileSource::open("/nonexistent/upload.bin").is_err());
+    }
+}
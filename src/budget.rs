@@ -0,0 +1,257 @@
+use crate::balance::{format_token_balance, parse_token_amount};
+use crate::config::{Budget, BudgetLimits};
+use crate::error::X402Error;
+use crate::ledger::LedgerEntry;
+use crate::payment::token_decimals;
+use chrono::{DateTime, Duration, Utc};
+
+/// Budgets are tracked with 18 decimals so that spend in tokens of any
+/// precision can be summed together.
+const BUDGET_DECIMALS: u8 = 18;
+
+/// One rolling budget window and the spend recorded in it.
+#[derive(Debug, Clone)]
+pub struct Window {
+    /// e.g. "daily" or "monthly (api.example.com)"
+    pub name: String,
+    period: Duration,
+    host: Option<String>,
+    pub limit: u128,
+    pub spent: u128,
+}
+
+impl Window {
+    pub fn remaining(&self) -> u128 {
+        self.limit.saturating_sub(self.spent)
+    }
+
+    /// e.g. "daily budget: 4.99 of 5.00 remaining"
+    pub fn describe(&self) -> String {
+        format!(
+            "{} budget: {} of {} remaining",
+            self.name,
+            format_budget(self.remaining()),
+            format_budget(self.limit)
+        )
+    }
+}
+
+/// Convert an atomic token amount to budget units.
+/// Returns `None` for assets whose decimals are unknown.
+pub fn to_budget_units(amount: u128, network: &str, asset: &str) -> Option<u128> {
+    let decimals = token_decimals(network, asset)?;
+    let scale = 10u128.checked_pow(BUDGET_DECIMALS.checked_sub(decimals)? as u32)?;
+    amount.checked_mul(scale)
+}
+
+/// Format budget units, dropping insignificant trailing zeros.
+fn format_budget(units: u128) -> String {
+    let formatted = format_token_balance(units, BUDGET_DECIMALS);
+    let (whole, frac) = formatted
+        .split_once('.')
+        .unwrap_or((formatted.as_str(), ""));
+    let frac = frac.trim_end_matches('0');
+    format!("{}.{:0<2}", whole, frac)
+}
+
+/// Check that every budget amount in the config parses.
+pub fn validate(budget: &Budget) -> Result<(), X402Error> {
+    let scopes = std::iter::once(&budget.global).chain(budget.hosts.values());
+    for limits in scopes {
+        for amount in [&limits.daily, &limits.monthly].into_iter().flatten() {
+            parse_token_amount(amount, BUDGET_DECIMALS)
+                .map_err(|_| X402Error::Config(format!("Invalid budget amount: {}", amount)))?;
+        }
+    }
+    Ok(())
+}
+
+fn scope_windows(limits: &BudgetLimits, host: Option<&str>) -> Vec<Window> {
+    let periods = [
+        ("daily", &limits.daily, Duration::days(1)),
+        ("monthly", &limits.monthly, Duration::days(30)),
+    ];
+    periods
+        .into_iter()
+        .filter_map(|(name, amount, period)| {
+            let limit = parse_token_amount(amount.as_deref()?, BUDGET_DECIMALS).ok()?;
+            Some(Window {
+                name: match host {
+                    Some(host) => format!("{} ({})", name, host),
+                    None => name.to_string(),
+                },
+                period,
+                host: host.map(|h| h.to_string()),
+                limit,
+                spent: 0,
+            })
+        })
+        .collect()
+}
+
+/// Budget windows that apply to a request to `host`, with no spend yet.
+pub fn windows(budget: &Budget, host: Option<&str>) -> Vec<Window> {
+    let mut windows = scope_windows(&budget.global, None);
+    if let Some(host) = host {
+        for (name, limits) in &budget.hosts {
+            if name.eq_ignore_ascii_case(host) {
+                windows.extend(scope_windows(limits, Some(host)));
+            }
+        }
+    }
+    windows
+}
+
+/// Add up the ledger spend falling into each window.
+/// Payments in assets with unknown decimals can't be valued and are skipped.
+pub fn tally(windows: &mut [Window], entries: &[LedgerEntry], now: DateTime<Utc>) {
+    for window in windows.iter_mut() {
+        let start = now - window.period;
+        window.spent = entries
+            .iter()
+            .filter(|e| e.timestamp > start)
+            .filter(|e| match window.host {
+                Some(ref host) => e.host().is_some_and(|h| h.eq_ignore_ascii_case(host)),
+                None => true,
+            })
+            .filter_map(|e| {
+                let amount = e.amount.parse().ok()?;
+                to_budget_units(amount, &e.network, &e.asset)
+            })
+            .fold(0u128, |total, amount| total.saturating_add(amount));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const USDC: &str = "0x036CbD53842c5426634e7929541eC2318f3dCF7e";
+
+    fn entry(hours_ago: i64, url: &str, amount: &str, now: DateTime<Utc>) -> LedgerEntry {
+        LedgerEntry {
+            timestamp: now - Duration::hours(hours_ago),
+            url: url.to_string(),
+            method: "GET".to_string(),
+            scheme: "exact".to_string(),
+            network: "base-sepolia".to_string(),
+            asset: USDC.to_string(),
+            amount: amount.to_string(),
+            pay_to: "0x209693Bc6afc0C5328bA36FaF03C514EF312287C".to_string(),
+            payer: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string(),
+            status: 200,
+            transaction: None,
+        }
+    }
+
+    fn budget(daily: &str, monthly: &str) -> BudgetLimits {
+        BudgetLimits {
+            daily: Some(daily.to_string()),
+            monthly: Some(monthly.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_to_budget_units() {
+        assert_eq!(
+            to_budget_units(10_000, "base-sepolia", USDC),
+            Some(10u128.pow(16))
+        );
+        assert_eq!(to_budget_units(10_000, "base-sepolia", "0x01"), None);
+    }
+
+    #[test]
+    fn test_format_budget() {
+        assert_eq!(format_budget(5 * 10u128.pow(18)), "5.00");
+        assert_eq!(format_budget(10u128.pow(16)), "0.01");
+        assert_eq!(format_budget(10u128.pow(12)), "0.000001");
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_amount() {
+        let mut hosts = BTreeMap::new();
+        hosts.insert("api.example.com".to_string(), budget("1", "lots"));
+        let budget = Budget {
+            global: budget("5", "100"),
+            hosts,
+        };
+        assert!(validate(&budget).is_err());
+    }
+
+    #[test]
+    fn test_tally_rolling_windows() {
+        let now = Utc::now();
+        let entries = vec![
+            entry(1, "https://a.com/x", "1000000", now),
+            entry(48, "https://a.com/x", "2000000", now),
+            entry(24 * 40, "https://a.com/x", "4000000", now),
+        ];
+        let budget = Budget {
+            global: budget("5", "100"),
+            hosts: BTreeMap::new(),
+        };
+
+        let mut windows = windows(&budget, Some("a.com"));
+        tally(&mut windows, &entries, now);
+        assert_eq!(
+            windows[0].describe(),
+            "daily budget: 4.00 of 5.00 remaining"
+        );
+        assert_eq!(
+            windows[1].describe(),
+            "monthly budget: 97.00 of 100.00 remaining"
+        );
+    }
+
+    #[test]
+    fn test_tally_saturates() {
+        let now = Utc::now();
+        // Each fits in budget units, but not their sum
+        let huge = (u128::MAX / 10u128.pow(12)).to_string();
+        let entries = vec![
+            entry(1, "https://a.com/x", &huge, now),
+            entry(1, "https://a.com/x", &huge, now),
+        ];
+        let budget = Budget {
+            global: budget("5", "100"),
+            hosts: BTreeMap::new(),
+        };
+
+        let mut windows = windows(&budget, None);
+        tally(&mut windows, &entries, now);
+        assert_eq!(windows[0].spent, u128::MAX);
+        assert_eq!(windows[0].remaining(), 0);
+    }
+
+    #[test]
+    fn test_tally_per_host() {
+        let now = Utc::now();
+        let entries = vec![
+            entry(1, "https://a.com/x", "1000000", now),
+            entry(1, "https://b.com/x", "2000000", now),
+        ];
+        let mut hosts = BTreeMap::new();
+        hosts.insert(
+            "b.com".to_string(),
+            BudgetLimits {
+                daily: Some("2.50".to_string()),
+                monthly: None,
+            },
+        );
+        let budget = Budget {
+            global: BudgetLimits::default(),
+            hosts,
+        };
+
+        let mut windows = windows(&budget, Some("b.com"));
+        tally(&mut windows, &entries, now);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(
+            windows[0].describe(),
+            "daily (b.com) budget: 0.50 of 2.50 remaining"
+        );
+
+        assert!(super::windows(&budget, Some("a.com")).is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+/// An EVM chain x402curl knows how to pay and query balances on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chain {
+    pub id: u64,
+    pub name: &'static str,
+    /// x402 v1 network name, e.g. "base-sepolia"
+    pub network: &'static str,
+    /// Default public RPC endpoint
+    pub rpc_url: &'static str,
+    /// Native (Circle-issued) USDC contract
+    pub usdc: &'static str,
+    pub usdc_decimals: u8,
+    /// Native gas token symbol (18 decimals)
+    pub native_symbol: &'static str,
+}
+
+pub const CHAINS: &[Chain] = &[
+    Chain {
+        id: 1,
+        name: "Ethereum",
+        network: "ethereum",
+        rpc_url: "https://ethereum-rpc.publicnode.com",
+        usdc: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 11155111,
+        name: "Sepolia",
+        network: "sepolia",
+        rpc_url: "https://ethereum-sepolia-rpc.publicnode.com",
+        usdc: "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 8453,
+        name: "Base",
+        network: "base",
+        rpc_url: "https://mainnet.base.org",
+        usdc: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 84532,
+        name: "Base Sepolia",
+        network: "base-sepolia",
+        rpc_url: "https://sepolia.base.org",
+        usdc: "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 10,
+        name: "Optimism",
+        network: "optimism",
+        rpc_url: "https://mainnet.optimism.io",
+        usdc: "0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 11155420,
+        name: "Optimism Sepolia",
+        network: "optimism-sepolia",
+        rpc_url: "https://sepolia.optimism.io",
+        usdc: "0x5fd84259d66Cd46123540766Be93DFE6D43130D7",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 42161,
+        name: "Arbitrum",
+        network: "arbitrum",
+        rpc_url: "https://arb1.arbitrum.io/rpc",
+        usdc: "0xaf88d065e77c8cC2239327C5EDb3A432268e5831",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 421614,
+        name: "Arbitrum Sepolia",
+        network: "arbitrum-sepolia",
+        rpc_url: "https://sepolia-rollup.arbitrum.io/rpc",
+        usdc: "0x75faf114eafb1BDbe2F0316DF893fd58CE46AA4d",
+        usdc_decimals: 6,
+        native_symbol: "ETH",
+    },
+    Chain {
+        id: 137,
+        name: "Polygon",
+        network: "polygon",
+        rpc_url: "https://polygon-rpc.com",
+        usdc: "0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359",
+        usdc_decimals: 6,
+        native_symbol: "POL",
+    },
+    Chain {
+        id: 80002,
+        name: "Polygon Amoy",
+        network: "polygon-amoy",
+        rpc_url: "https://rpc-amoy.polygon.technology",
+        usdc: "0x41E94Eb019C0762f9Bfcf9Fb1E58725BfB0e7582",
+        usdc_decimals: 6,
+        native_symbol: "POL",
+    },
+];
+
+/// A Solana cluster x402curl knows how to pay and query balances on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolanaChain {
+    pub name: &'static str,
+    /// x402 v1 network name, e.g. "solana-devnet"
+    pub network: &'static str,
+    /// x402 v2 CAIP-2 identifier (genesis hash prefix)
+    pub caip2: &'static str,
+    /// Default public RPC endpoint
+    pub rpc_url: &'static str,
+    /// USDC SPL token mint
+    pub usdc: &'static str,
+    pub usdc_decimals: u8,
+}
+
+pub const SOLANA_CHAINS: &[SolanaChain] = &[
+    SolanaChain {
+        name: "Solana",
+        network: "solana",
+        caip2: "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp",
+        rpc_url: "https://api.mainnet-beta.solana.com",
+        usdc: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+        usdc_decimals: 6,
+    },
+    SolanaChain {
+        name: "Solana Devnet",
+        network: "solana-devnet",
+        caip2: "solana:EtWTRABZaYq6iMfeYKouRu166VoYDNzN",
+        rpc_url: "https://api.devnet.solana.com",
+        usdc: "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+        usdc_decimals: 6,
+    },
+];
+
+/// Look up a chain by EVM chain ID.
+pub fn by_id(id: u64) -> Option<&'static Chain> {
+    CHAINS.iter().find(|c| c.id == id)
+}
+
+/// Look up a chain by x402 network: a v1 name ("base-sepolia") or a v2
+/// CAIP-2 identifier ("eip155:84532").
+pub fn by_network(network: &str) -> Option<&'static Chain> {
+    match network.strip_prefix("eip155:") {
+        Some(id) => by_id(id.parse().ok()?),
+        None => CHAINS
+            .iter()
+            .find(|c| c.network.eq_ignore_ascii_case(network)),
+    }
+}
+
+/// Look up a Solana cluster by x402 network: a v1 name ("solana-devnet")
+/// or a v2 CAIP-2 identifier ("solana:EtWTRABZaYq6iMfeYKouRu166VoYDNzN").
+pub fn solana_by_network(network: &str) -> Option<&'static SolanaChain> {
+    SOLANA_CHAINS
+        .iter()
+        .find(|c| c.network.eq_ignore_ascii_case(network) || c.caip2 == network)
+}
+
+impl Chain {
+    /// Whether `asset` is this chain's USDC contract.
+    pub fn is_usdc(&self, asset: &str) -> bool {
+        self.usdc.eq_ignore_ascii_case(asset)
+    }
+}
+
+impl SolanaChain {
+    /// Whether `asset` is this cluster's USDC mint. Base58 is case-sensitive.
+    pub fn is_usdc(&self, asset: &str) -> bool {
+        self.usdc == asset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_by_id() {
+        assert_eq!(by_id(8453).unwrap().name, "Base");
+        assert_eq!(
+            by_id(84532).unwrap().usdc,
+            "0x036CbD53842c5426634e7929541eC2318f3dCF7e"
+        );
+        assert_eq!(by_id(42161).unwrap().network, "arbitrum");
+        assert!(by_id(99999).is_none());
+    }
+
+    #[test]
+    fn test_by_network() {
+        assert_eq!(by_network("base-sepolia").unwrap().id, 84532);
+        assert_eq!(by_network("eip155:137").unwrap().name, "Polygon");
+        assert_eq!(by_network("Optimism").unwrap().id, 10);
+        assert!(by_network("eip155:99999").is_none());
+        assert!(by_network("solana-devnet").is_none());
+    }
+
+    #[test]
+    fn test_solana_by_network() {
+        assert_eq!(
+            solana_by_network("solana-devnet").unwrap().name,
+            "Solana Devnet"
+        );
+        assert_eq!(
+            solana_by_network("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp")
+                .unwrap()
+                .network,
+            "solana"
+        );
+        assert!(solana_by_network("base").is_none());
+        assert!(by_network("solana").is_none());
+    }
+
+    #[test]
+    fn test_registry_is_consistent() {
+        for chain in CHAINS {
+            assert_eq!(by_id(chain.id), Some(chain));
+            assert_eq!(by_network(chain.network), Some(chain));
+            assert_eq!(by_network(&format!("eip155:{}", chain.id)), Some(chain));
+            assert!(chain.usdc.starts_with("0x") && chain.usdc.len() == 42);
+        }
+    }
+}
@@ -1,34 +1,70 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(name = "x402curl")]
 #[command(about = "curl with automatic x402 payment handling")]
 #[command(version)]
+#[command(subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// URL to request
-    #[arg(required_unless_present = "x402_balance")]
+    #[arg(required_unless_present_any = ["x402_balance", "x402_ledger"])]
     pub url: Option<String>,
 
-    /// HTTP method (GET, POST, PUT, DELETE, etc.)
-    #[arg(short = 'X', long = "request", default_value = "GET")]
-    pub method: String,
+    /// HTTP method (GET, POST, PUT, DELETE, etc.; default: GET, HEAD with -I, or PUT with -T)
+    #[arg(short = 'X', long = "request")]
+    pub method: Option<String>,
 
     /// Add header (can be used multiple times)
     #[arg(short = 'H', long = "header", action = clap::ArgAction::Append)]
     pub headers: Vec<String>,
 
-    /// Request body data (use @filename to read from file)
+    /// Request body data (use @filename to read from file, @- for stdin)
     #[arg(short = 'd', long = "data")]
     pub data: Option<String>,
 
-    /// Send data without processing (use @filename to read from file)
+    /// Send data without processing (use @filename to read from file, @- for stdin)
     #[arg(long = "data-binary")]
     pub data_binary: Option<String>,
 
+    /// Upload a file (- for stdin) as the request body, with PUT unless -X is given
+    #[arg(
+        short = 'T',
+        long = "upload-file",
+        conflicts_with_all = ["data", "data_binary", "form"]
+    )]
+    pub upload_file: Option<String>,
+
     /// Write output to file instead of stdout
     #[arg(short = 'o', long = "output")]
     pub output: Option<String>,
 
+    /// Include the response headers in the output, before the body
+    #[arg(short = 'i', long = "include")]
+    pub include: bool,
+
+    /// Fetch the headers only, with a HEAD request unless -X is given
+    #[arg(
+        short = 'I',
+        long = "head",
+        conflicts_with_all = ["data", "data_binary", "form", "upload_file"]
+    )]
+    pub head: bool,
+
+    /// Write the response headers to this file (- for stdout)
+    #[arg(short = 'D', long = "dump-header")]
+    pub dump_header: Option<String>,
+
+    /// Print FORMAT after the transfer: curl variables such as %{http_code} and %{time_total}, plus %{x402_paid}, %{x402_amount}, %{x402_network} and %{x402_tx_hash} (@file or @- to read it)
+    #[arg(short = 'w', long = "write-out", value_name = "FORMAT")]
+    pub write_out: Option<String>,
+
+    /// Disable output buffering: write each chunk as soon as it arrives
+    #[arg(short = 'N', long = "no-buffer")]
+    pub no_buffer: bool,
+
     /// Fail silently on HTTP errors (exit non-zero)
     #[arg(short = 'f', long = "fail")]
     pub fail: bool,
@@ -53,11 +89,15 @@ pub struct Args {
     #[arg(short = 'L', long = "location")]
     pub location: bool,
 
-    /// Override private key
+    /// Use the [profiles.NAME] section of ~/.x402/config
+    #[arg(long = "x402-profile")]
+    pub x402_profile: Option<String>,
+
+    /// Override private key (or keyring:NAME to use a key from the OS keyring)
     #[arg(long = "x402-key")]
     pub x402_key: Option<String>,
 
-    /// Path to Ethereum keystore (wallet.json) file
+    /// Path to Ethereum keystore (wallet.json) file, or the name of a wallet in ~/.x402/wallets
     #[arg(long = "x402-wallet")]
     pub x402_wallet: Option<String>,
 
@@ -65,25 +105,183 @@ pub struct Args {
     #[arg(long = "x402-wallet-password")]
     pub x402_wallet_password: Option<String>,
 
+    /// File containing the password for the keystore wallet file
+    #[arg(
+        long = "x402-wallet-password-file",
+        conflicts_with = "x402_wallet_password"
+    )]
+    pub x402_wallet_password_file: Option<String>,
+
+    /// External signing daemon holding the EVM key (http(s)://host:port or unix:///path)
+    #[arg(long = "x402-signer")]
+    pub x402_signer: Option<String>,
+
+    /// Override Solana private key (base58)
+    #[arg(long = "x402-solana-key")]
+    pub x402_solana_key: Option<String>,
+
+    /// Path to a Solana JSON keypair file
+    #[arg(long = "x402-solana-keypair")]
+    pub x402_solana_keypair: Option<String>,
+
+    /// Solana RPC endpoint used to build payments (default: the cluster's public RPC)
+    #[arg(long = "x402-solana-rpc-url")]
+    pub x402_solana_rpc_url: Option<String>,
+
     /// Show payment requirements without paying
     #[arg(long = "x402-dry-run")]
     pub x402_dry_run: bool,
 
+    /// Maximum amount to pay per request, in token units (e.g. 0.10)
+    #[arg(long = "x402-max-amount")]
+    pub x402_max_amount: Option<String>,
+
+    /// Print payment requirements, receipts, balances and errors as one JSON document on stderr
+    #[arg(long = "x402-json")]
+    pub x402_json: bool,
+
+    /// Print the data of each server-sent event (text/event-stream) as a line of JSON
+    #[arg(long = "x402-sse-json")]
+    pub x402_sse_json: bool,
+
+    /// Write the payment receipt and settlement proof to a JSON file
+    #[arg(long = "x402-receipt")]
+    pub x402_receipt: Option<String>,
+
+    /// Preferred networks when several are accepted, most preferred first (e.g. base,polygon)
+    #[arg(long = "x402-prefer-network", value_delimiter = ',')]
+    pub x402_prefer_network: Vec<String>,
+
+    /// Preferred assets when several are accepted: contract addresses or USDC
+    #[arg(long = "x402-prefer-asset", value_delimiter = ',')]
+    pub x402_prefer_asset: Vec<String>,
+
+    /// Pay with the cheapest accepted option
+    #[arg(long = "x402-cheapest")]
+    pub x402_cheapest: bool,
+
+    /// Only pay on networks where the wallet is known to hold enough funds
+    #[arg(long = "x402-funded-only")]
+    pub x402_funded_only: bool,
+
     /// Prompt before making payment
     #[arg(long = "confirm")]
     pub confirm: bool,
 
-    /// Query wallet USDC balance on Base network
+    /// Approve each payment by running this program (with arguments, not through a shell) with the requirements as JSON on stdin; exit 0 approves
+    #[arg(long = "x402-approve-cmd")]
+    pub x402_approve_cmd: Option<String>,
+
+    /// Query wallet USDC balance (default: Base mainnet)
     #[arg(long = "x402-balance")]
     pub x402_balance: bool,
 
+    /// With --x402-balance, query USDC and native balances on every supported network
+    #[arg(
+        long = "all-networks",
+        requires = "x402_balance",
+        conflicts_with_all = ["x402_rpc_url", "x402_network", "x402_token"]
+    )]
+    pub all_networks: bool,
+
     /// Override RPC endpoint URL (default: https://mainnet.base.org)
     #[arg(long = "x402-rpc-url")]
     pub x402_rpc_url: Option<String>,
 
+    /// Network to query with --x402-balance, using its public RPC (e.g. base-sepolia, solana)
+    #[arg(long = "x402-network")]
+    pub x402_network: Option<String>,
+
     /// Override ERC-20 token contract address (default: USDC on detected chain)
     #[arg(long = "x402-token")]
     pub x402_token: Option<String>,
+
+    /// List payments recorded in the local ledger (~/.x402/ledger.jsonl)
+    #[arg(long = "x402-ledger")]
+    pub x402_ledger: bool,
+
+    /// Only list ledger entries on or after this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long = "x402-ledger-since", requires = "x402_ledger")]
+    pub x402_ledger_since: Option<String>,
+
+    /// Only list ledger entries up to this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long = "x402-ledger-until", requires = "x402_ledger")]
+    pub x402_ledger_until: Option<String>,
+
+    /// Only list ledger entries for this host
+    #[arg(long = "x402-ledger-host", requires = "x402_ledger")]
+    pub x402_ledger_host: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Manage private keys stored in the OS keyring
+    #[command(subcommand)]
+    Keyring(KeyringCommand),
+    /// Create and manage keystore wallets in ~/.x402/wallets
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+    /// Inspect the effective configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective settings, merged from flags, environment and config files
+    Show {
+        /// Show where each setting came from
+        #[arg(long)]
+        explain: bool,
+        /// Use the [profiles.NAME] section of the config files
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeyringCommand {
+    /// Store a private key read from stdin under NAME
+    Import { name: String },
+    /// List stored keys and their addresses
+    List,
+    /// Remove the key stored under NAME
+    Remove { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WalletCommand {
+    /// Create a wallet with a new random key under NAME
+    New {
+        name: String,
+        /// Keystore password (default: X402_WALLET_PASSWORD, or prompt)
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Encrypt a private key (or keyring:NAME reference) read from stdin under NAME
+    Import {
+        name: String,
+        /// Keystore password (default: X402_WALLET_PASSWORD, or prompt)
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Print the address of the wallet NAME
+    Address {
+        name: String,
+        /// Keystore password, only needed if the keystore doesn't record its address
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Change the password of the wallet NAME
+    Rekey {
+        name: String,
+        /// Current password (default: X402_WALLET_PASSWORD, or prompt)
+        #[arg(long)]
+        password: Option<String>,
+        /// New password (default: X402_WALLET_NEW_PASSWORD, or prompt)
+        #[arg(long = "new-password")]
+        new_password: Option<String>,
+    },
 }
 
 impl Args {
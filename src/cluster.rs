@@ -0,0 +1,47 @@
+use crate::chains::{self, SolanaChain};
+use x402_types::proto;
+use x402_types::scheme::client::{PaymentCandidate, X402SchemeClient};
+use x402_types::scheme::X402SchemeId;
+
+/// Solana scheme client limited to one cluster.
+///
+/// The SDK's Solana clients build payments with a single RPC client but
+/// accept options on any cluster. Registering one `Cluster` per cluster, each
+/// with that cluster's RPC, makes a devnet payment fetch its blockhash and
+/// token accounts from devnet rather than mainnet.
+pub struct Cluster<C> {
+    chain: &'static SolanaChain,
+    inner: C,
+}
+
+impl<C> Cluster<C> {
+    pub fn new(chain: &'static SolanaChain, inner: C) -> Self {
+        Self { chain, inner }
+    }
+}
+
+impl<C: X402SchemeId> X402SchemeId for Cluster<C> {
+    fn x402_version(&self) -> u8 {
+        self.inner.x402_version()
+    }
+
+    fn namespace(&self) -> &str {
+        self.inner.namespace()
+    }
+
+    fn scheme(&self) -> &str {
+        self.inner.scheme()
+    }
+}
+
+impl<C: X402SchemeClient> X402SchemeClient for Cluster<C> {
+    fn accept(&self, payment_required: &proto::PaymentRequired) -> Vec<PaymentCandidate> {
+        self.inner
+            .accept(payment_required)
+            .into_iter()
+            .filter(|candidate| {
+                chains::solana_by_network(&candidate.chain_id.to_string()) == Some(self.chain)
+            })
+            .collect()
+    }
+}
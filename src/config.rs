@@ -1,125 +1,780 @@
+use crate::cli::Args;
 use crate::error::X402Error;
+use crate::secrets;
+use crate::signer::{EvmSigner, RemoteSigner};
+use crate::wallet;
 use alloy_signer_local::PrivateKeySigner;
+use solana_keypair::Keypair;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Per-project config file, looked up from the current directory upwards.
+const PROJECT_CONFIG: &str = ".x402.toml";
+
 #[derive(Debug, Default)]
 pub struct Config {
     pub private_key: Option<String>,
     pub wallet_path: Option<String>,
     pub wallet_password: Option<String>,
+    /// File holding the keystore password, read instead of passing it inline
+    pub wallet_password_file: Option<String>,
+    /// External signing daemon (`http(s)://...` or `unix:///path`)
+    pub signer_url: Option<String>,
+    /// Base58-encoded Solana secret key (64 bytes)
+    pub solana_private_key: Option<String>,
+    /// Path to a Solana JSON keypair file (as written by `solana-keygen`)
+    pub solana_keypair_path: Option<String>,
+    /// EVM RPC endpoint for balance checks
+    pub rpc_url: Option<String>,
+    /// Solana RPC endpoint for balance checks and building payments
+    pub solana_rpc_url: Option<String>,
+    /// Profile selected with --x402-profile or X402_PROFILE
+    pub profile: Option<String>,
     pub verbose: bool,
     pub confirm: bool,
+    /// Command that approves each payment by exiting 0
+    pub approve_cmd: Option<String>,
+    pub limits: Limits,
+    pub budget: Budget,
+    pub selection: Selection,
+    pub policy: Policy,
+    /// Where each setting came from, keyed by its name in the config file
+    /// (e.g. "limits.max_amount"). Settings left at their default are absent.
+    pub sources: BTreeMap<String, Source>,
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct ConfigFile {
+/// Where a setting came from, highest precedence first.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Cli,
+    /// Process environment, including variables loaded from `.env`
+    Env,
+    Project(PathBuf),
+    Global(PathBuf),
+}
+
+impl Source {
+    /// Whether this source may redirect signing and RPC or raise spending
+    /// caps. A project file comes with whatever checkout x402curl happens to
+    /// run in, so it may not.
+    fn trusted(&self) -> bool {
+        !matches!(self, Source::Project(_))
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Cli => write!(f, "command line"),
+            Source::Env => write!(f, "environment"),
+            Source::Project(path) | Source::Global(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Settings from one source: the command line, the environment, or a
+/// profile of a config file (its top level being the default profile).
+/// Unset fields fall through to the next source.
+#[derive(Debug, Default, serde::Deserialize)]
+struct Layer {
+    /// Raw key, or `keyring:<name>` to look it up in the OS keyring
     private_key: Option<String>,
     wallet_path: Option<String>,
     wallet_password: Option<String>,
+    wallet_password_file: Option<String>,
+    signer: Option<String>,
+    solana_private_key: Option<String>,
+    solana_keypair_path: Option<String>,
+    rpc_url: Option<String>,
+    solana_rpc_url: Option<String>,
+    approve_cmd: Option<String>,
+    #[serde(default)]
+    defaults: LayerDefaults,
+    #[serde(default)]
+    limits: Limits,
+    #[serde(default)]
+    budget: Budget,
+    #[serde(default)]
+    selection: LayerSelection,
     #[serde(default)]
-    defaults: ConfigDefaults,
+    policy: LayerPolicy,
 }
 
-#[derive(Debug, Default, serde::Deserialize)]
-struct ConfigDefaults {
+#[derive(Debug, serde::Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    default: Layer,
     #[serde(default)]
-    verbose: bool,
+    profiles: BTreeMap<String, Layer>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct LayerDefaults {
+    verbose: Option<bool>,
+    confirm: Option<bool>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct LayerSelection {
+    networks: Option<Vec<String>>,
+    assets: Option<Vec<String>>,
+    cheapest: Option<bool>,
+    funded_only: Option<bool>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct LayerPolicy {
+    allow_hosts: Option<Vec<String>>,
+    deny_hosts: Option<Vec<String>>,
+    allow_pay_to: Option<Vec<String>>,
+    deny_pay_to: Option<Vec<String>>,
+    allow_assets: Option<Vec<String>>,
+    deny_assets: Option<Vec<String>>,
+}
+
+/// Spending limits from the `[limits]` section of the config file.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+pub struct Limits {
+    /// Maximum amount per payment, in token units (e.g. "0.10")
+    pub max_amount: Option<String>,
+}
+
+/// Rolling spending budgets, in token units (e.g. "5.00").
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+pub struct BudgetLimits {
+    /// Maximum spend over the last 24 hours
+    pub daily: Option<String>,
+    /// Maximum spend over the last 30 days
+    pub monthly: Option<String>,
+}
+
+/// Budgets from the `[budget]` section of the config file, with optional
+/// per-host budgets under `[budget.hosts."<host>"]`.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+pub struct Budget {
+    #[serde(flatten)]
+    pub global: BudgetLimits,
     #[serde(default)]
-    confirm: bool,
+    pub hosts: BTreeMap<String, BudgetLimits>,
 }
 
-impl Config {
-    /// Load configuration from hierarchy:
-    /// 1. --x402-key CLI flag
-    /// 2. X402_PRIVATE_KEY env var
-    /// 3. .env file X402_PRIVATE_KEY
-    /// 4. --x402-wallet + --x402-wallet-password CLI flags
-    /// 5. X402_WALLET + X402_WALLET_PASSWORD env vars (including .env)
-    /// 6. ~/.x402/config
-    pub fn load(
-        cli_key: Option<&str>,
-        cli_wallet: Option<&str>,
-        cli_wallet_password: Option<&str>,
-    ) -> Result<Self, X402Error> {
-        // Priority 1: CLI private key flag
-        if let Some(key) = cli_key {
-            return Ok(Config {
-                private_key: Some(key.to_string()),
-                ..Default::default()
-            });
+/// Which payment option to prefer when a server accepts several, from the
+/// `[selection]` section of the config file and the `--x402-prefer-*`,
+/// `--x402-cheapest` and `--x402-funded-only` flags.
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    /// Preferred networks, most preferred first (e.g. "base" or "eip155:8453")
+    pub networks: Vec<String>,
+    /// Preferred assets, most preferred first (contract address or "USDC")
+    pub assets: Vec<String>,
+    /// Prefer the cheapest option
+    pub cheapest: bool,
+    /// Only pay on networks where the wallet is known to hold enough funds
+    pub funded_only: bool,
+}
+
+/// Who may be paid, from the `[policy]` section of the config file. An
+/// empty allow list allows everything; a deny list entry always wins.
+#[derive(Debug, Default, Clone)]
+pub struct Policy {
+    /// Host globs (e.g. "*.example.com") of servers that may be paid
+    pub allow_hosts: Vec<String>,
+    pub deny_hosts: Vec<String>,
+    /// Recipient (`payTo`) addresses
+    pub allow_pay_to: Vec<String>,
+    pub deny_pay_to: Vec<String>,
+    /// Asset contract addresses, or "USDC"
+    pub allow_assets: Vec<String>,
+    pub deny_assets: Vec<String>,
+}
+
+impl Policy {
+    /// Whether the policy allows everything.
+    pub fn is_empty(&self) -> bool {
+        [
+            &self.allow_hosts,
+            &self.deny_hosts,
+            &self.allow_pay_to,
+            &self.deny_pay_to,
+            &self.allow_assets,
+            &self.deny_assets,
+        ]
+        .iter()
+        .all(|list| list.is_empty())
+    }
+}
+
+fn unknown_profile(name: &str) -> X402Error {
+    X402Error::Config(format!(
+        "Unknown profile '{}'. Add a [profiles.{}] section to ~/.x402/config or {}.",
+        name, name, PROJECT_CONFIG
+    ))
+}
+
+fn host_key(host: &str, field: &str) -> String {
+    format!("budget.hosts.{:?}.{}", host, field)
+}
+
+/// Whether token amount `a` is below `b`. An amount that doesn't parse
+/// counts as lower when it's `a`, so the invalid value is the one reported.
+fn lowers(a: &str, b: &str) -> bool {
+    let parse = |amount: &str| crate::balance::parse_token_amount(amount, 18);
+    match (parse(a), parse(b)) {
+        (Ok(a), Ok(b)) => a < b,
+        (Err(_), _) => true,
+        (Ok(_), Err(_)) => false,
+    }
+}
+
+impl Layer {
+    fn from_args(args: &Args) -> Self {
+        let flag = |set: bool| set.then_some(true);
+        let list = |values: &Vec<String>| (!values.is_empty()).then(|| values.clone());
+        Layer {
+            private_key: args.x402_key.clone(),
+            wallet_path: args.x402_wallet.clone(),
+            wallet_password: args.x402_wallet_password.clone(),
+            wallet_password_file: args.x402_wallet_password_file.clone(),
+            signer: args.x402_signer.clone(),
+            solana_private_key: args.x402_solana_key.clone(),
+            solana_keypair_path: args.x402_solana_keypair.clone(),
+            rpc_url: args.x402_rpc_url.clone(),
+            solana_rpc_url: args.x402_solana_rpc_url.clone(),
+            approve_cmd: args.x402_approve_cmd.clone(),
+            defaults: LayerDefaults {
+                verbose: flag(args.verbose),
+                confirm: flag(args.confirm),
+            },
+            limits: Limits {
+                max_amount: args.x402_max_amount.clone(),
+            },
+            budget: Budget::default(),
+            selection: LayerSelection {
+                networks: list(&args.x402_prefer_network),
+                assets: list(&args.x402_prefer_asset),
+                cheapest: flag(args.x402_cheapest),
+                funded_only: flag(args.x402_funded_only),
+            },
+            policy: LayerPolicy::default(),
+        }
+    }
+
+    /// Settings from `X402_*` environment variables, including `.env`.
+    fn from_env() -> Self {
+        let _ = dotenvy::dotenv();
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Layer {
+            private_key: var("X402_PRIVATE_KEY"),
+            wallet_path: var("X402_WALLET"),
+            wallet_password: var("X402_WALLET_PASSWORD"),
+            wallet_password_file: var("X402_WALLET_PASSWORD_FILE"),
+            signer: var("X402_SIGNER"),
+            solana_private_key: var("X402_SOLANA_PRIVATE_KEY"),
+            solana_keypair_path: var("X402_SOLANA_KEYPAIR"),
+            rpc_url: var("X402_RPC_URL"),
+            solana_rpc_url: var("X402_SOLANA_RPC_URL"),
+            approve_cmd: var("X402_APPROVE_CMD"),
+            ..Default::default()
+        }
+    }
+
+    /// Settings of `profile` (or of the top level) in a config file, if the
+    /// file defines that profile.
+    fn from_file(path: &Path, profile: Option<&str>) -> Result<Option<Self>, X402Error> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            X402Error::Config(format!(
+                "Failed to read config file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let mut config_file: ConfigFile = toml::from_str(&content).map_err(|e| {
+            X402Error::Config(format!(
+                "Failed to parse config file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(match profile {
+            Some(name) => config_file.profiles.remove(name),
+            None => Some(config_file.default),
+        })
+    }
+}
+
+/// Resolves each setting from the first layer that sets it, recording
+/// where it came from.
+struct Layers {
+    layers: Vec<(Source, Layer)>,
+    sources: BTreeMap<String, Source>,
+    /// Settings a project file set but isn't allowed to, and why
+    ignored: Vec<(String, PathBuf, &'static str)>,
+}
+
+impl Layers {
+    /// The first trusted, or untrusted, layer that sets a value.
+    fn first<T>(&self, trusted: bool, get: impl Fn(&Layer) -> Option<T>) -> Option<(Source, T)> {
+        self.layers
+            .iter()
+            .filter(|(source, _)| source.trusted() == trusted)
+            .find_map(|(source, layer)| get(layer).map(|value| (source.clone(), value)))
+    }
+
+    fn ignore(&mut self, key: &str, source: Source, reason: &'static str) {
+        if let Source::Project(path) = source {
+            self.ignored.push((key.to_string(), path, reason));
+        }
+    }
+
+    /// Like [`Layers::pick`], but a project file can't set the value.
+    fn pick_trusted<T>(&mut self, key: &str, get: impl Fn(&Layer) -> Option<T>) -> Option<T> {
+        if let Some((source, _)) = self.first(false, &get) {
+            self.ignore(
+                key,
+                source,
+                "only the command line, environment or ~/.x402/config can set it",
+            );
         }
+        let (source, value) = self.first(true, &get)?;
+        self.sources.insert(key.to_string(), source);
+        Some(value)
+    }
+
+    /// A limit a project file may tighten but not loosen: its value is used
+    /// when `tighter` than the one from trusted sources, or when none of
+    /// them sets it.
+    fn pick_tighter<T>(
+        &mut self,
+        key: &str,
+        get: impl Fn(&Layer) -> Option<T>,
+        tighter: impl Fn(&T, &T) -> bool,
+    ) -> Option<T> {
+        let (source, value) = match (self.first(false, &get), self.first(true, &get)) {
+            (Some(project), Some(trusted)) if !tighter(&project.1, &trusted.1) => {
+                self.ignore(key, project.0, "a project config can only tighten it");
+                trusted
+            }
+            (Some(project), _) => project,
+            (None, Some(trusted)) => trusted,
+            (None, None) => return None,
+        };
+        self.sources.insert(key.to_string(), source);
+        Some(value)
+    }
+
+    /// A spending cap, in token units, which a project file may only lower.
+    fn pick_cap(&mut self, key: &str, get: impl Fn(&Layer) -> Option<String>) -> Option<String> {
+        self.pick_tighter(key, get, |project, trusted| lowers(project, trusted))
+    }
+
+    /// A safeguard a project file may switch on but not off.
+    fn pick_safeguard(&mut self, key: &str, get: impl Fn(&Layer) -> Option<bool>) -> Option<bool> {
+        self.pick_tighter(key, get, |project, trusted| *project || !*trusted)
+    }
+
+    /// A policy allow list, which a project file may only narrow: to some of
+    /// the entries of the trusted list, or from allowing everything.
+    fn pick_allow(
+        &mut self,
+        key: &str,
+        get: impl Fn(&Layer) -> Option<Vec<String>>,
+    ) -> Vec<String> {
+        self.pick_tighter(key, get, |project, trusted| {
+            !project.is_empty()
+                && (trusted.is_empty() || project.iter().all(|entry| trusted.contains(entry)))
+        })
+        .unwrap_or_default()
+    }
 
-        // Priority 2: X402_PRIVATE_KEY env var
-        if let Ok(key) = std::env::var("X402_PRIVATE_KEY") {
-            if !key.is_empty() {
-                return Ok(Config {
-                    private_key: Some(key),
-                    ..Default::default()
-                });
+    /// A policy deny list: the entries of every layer.
+    fn pick_deny(&mut self, key: &str, get: impl Fn(&Layer) -> Option<Vec<String>>) -> Vec<String> {
+        let mut union: Vec<String> = Vec::new();
+        for (source, layer) in &self.layers {
+            let Some(entries) = get(layer) else {
+                continue;
+            };
+            self.sources
+                .entry(key.to_string())
+                .or_insert_with(|| source.clone());
+            for entry in entries {
+                if !union.contains(&entry) {
+                    union.push(entry);
+                }
             }
         }
+        union
+    }
 
-        // Priority 3: .env file -> X402_PRIVATE_KEY
-        let _ = dotenvy::dotenv();
-        if let Ok(key) = std::env::var("X402_PRIVATE_KEY") {
-            if !key.is_empty() {
-                return Ok(Config {
-                    private_key: Some(key),
-                    ..Default::default()
-                });
+    fn pick<T>(&mut self, key: &str, get: impl Fn(&Layer) -> Option<T>) -> Option<T> {
+        let (source, value) = self
+            .layers
+            .iter()
+            .find_map(|(source, layer)| get(layer).map(|value| (source, value)))?;
+        self.sources.insert(key.to_string(), source.clone());
+        Some(value)
+    }
+
+    /// Two settings that only make sense together, such as a private key and
+    /// a keystore, both come from the first layer that sets either of them.
+    /// Otherwise a key in the global config would override `--x402-wallet`.
+    fn pick_either(
+        &mut self,
+        keys: [&str; 2],
+        get: impl Fn(&Layer) -> (Option<String>, Option<String>),
+    ) -> (Option<String>, Option<String>) {
+        for (source, layer) in &self.layers {
+            let (first, second) = get(layer);
+            if first.is_none() && second.is_none() {
+                continue;
+            }
+            for (key, value) in keys.iter().zip([&first, &second]) {
+                if value.is_some() {
+                    self.sources.insert(key.to_string(), source.clone());
+                }
             }
+            return (first, second);
         }
+        (None, None)
+    }
 
-        // Priority 4: CLI wallet flags
-        if let Some(wallet) = cli_wallet {
-            return Ok(Config {
-                wallet_path: Some(wallet.to_string()),
-                wallet_password: cli_wallet_password.map(|p| p.to_string()),
-                ..Default::default()
-            });
+    fn pick_budget(&mut self, host: Option<&str>) -> BudgetLimits {
+        let limits = |layer: &Layer| match host {
+            Some(host) => layer.budget.hosts.get(host).cloned(),
+            None => Some(layer.budget.global.clone()),
+        };
+        let key = |field: &str| match host {
+            Some(host) => host_key(host, field),
+            None => format!("budget.{}", field),
+        };
+        BudgetLimits {
+            daily: self.pick_cap(&key("daily"), |l| limits(l).and_then(|b| b.daily)),
+            monthly: self.pick_cap(&key("monthly"), |l| limits(l).and_then(|b| b.monthly)),
         }
+    }
+
+    /// The merged settings, and those ignored from project files.
+    fn resolve(mut self) -> (Config, Vec<(String, PathBuf, &'static str)>) {
+        let (private_key, wallet_path) = self.pick_either(["private_key", "wallet_path"], |l| {
+            (l.private_key.clone(), l.wallet_path.clone())
+        });
+        let (solana_private_key, solana_keypair_path) = self
+            .pick_either(["solana_private_key", "solana_keypair_path"], |l| {
+                (l.solana_private_key.clone(), l.solana_keypair_path.clone())
+            });
+        let (wallet_password, wallet_password_file) = self
+            .pick_either(["wallet_password", "wallet_password_file"], |l| {
+                (l.wallet_password.clone(), l.wallet_password_file.clone())
+            });
+
+        let hosts: BTreeSet<String> = self
+            .layers
+            .iter()
+            .flat_map(|(_, l)| l.budget.hosts.keys().cloned())
+            .collect();
+        let budget = Budget {
+            global: self.pick_budget(None),
+            hosts: hosts
+                .into_iter()
+                .map(|host| {
+                    let limits = self.pick_budget(Some(&host));
+                    (host, limits)
+                })
+                .collect(),
+        };
+
+        let config = Config {
+            private_key,
+            wallet_path,
+            wallet_password,
+            wallet_password_file,
+            signer_url: self.pick_trusted("signer", |l| l.signer.clone()),
+            solana_private_key,
+            solana_keypair_path,
+            rpc_url: self.pick_trusted("rpc_url", |l| l.rpc_url.clone()),
+            solana_rpc_url: self.pick_trusted("solana_rpc_url", |l| l.solana_rpc_url.clone()),
+            profile: None,
+            verbose: self
+                .pick("defaults.verbose", |l| l.defaults.verbose)
+                .unwrap_or(false),
+            confirm: self
+                .pick_safeguard("defaults.confirm", |l| l.defaults.confirm)
+                .unwrap_or(false),
+            approve_cmd: self.pick_trusted("approve_cmd", |l| l.approve_cmd.clone()),
+            limits: Limits {
+                max_amount: self.pick_cap("limits.max_amount", |l| l.limits.max_amount.clone()),
+            },
+            budget,
+            selection: Selection {
+                networks: self
+                    .pick("selection.networks", |l| l.selection.networks.clone())
+                    .unwrap_or_default(),
+                assets: self
+                    .pick("selection.assets", |l| l.selection.assets.clone())
+                    .unwrap_or_default(),
+                cheapest: self
+                    .pick("selection.cheapest", |l| l.selection.cheapest)
+                    .unwrap_or(false),
+                funded_only: self
+                    .pick_safeguard("selection.funded_only", |l| l.selection.funded_only)
+                    .unwrap_or(false),
+            },
+            policy: Policy {
+                allow_hosts: self
+                    .pick_allow("policy.allow_hosts", |l| l.policy.allow_hosts.clone()),
+                deny_hosts: self.pick_deny("policy.deny_hosts", |l| l.policy.deny_hosts.clone()),
+                allow_pay_to: self
+                    .pick_allow("policy.allow_pay_to", |l| l.policy.allow_pay_to.clone()),
+                deny_pay_to: self.pick_deny("policy.deny_pay_to", |l| l.policy.deny_pay_to.clone()),
+                allow_assets: self
+                    .pick_allow("policy.allow_assets", |l| l.policy.allow_assets.clone()),
+                deny_assets: self.pick_deny("policy.deny_assets", |l| l.policy.deny_assets.clone()),
+            },
+            sources: self.sources,
+        };
+        (config, self.ignored)
+    }
+}
 
-        // Priority 5: X402_WALLET env var (including from .env loaded above)
-        if let Ok(wallet) = std::env::var("X402_WALLET") {
-            if !wallet.is_empty() {
-                let password = std::env::var("X402_WALLET_PASSWORD").ok();
-                return Ok(Config {
-                    wallet_path: Some(wallet),
-                    wallet_password: password,
-                    ..Default::default()
-                });
+impl Config {
+    /// Load configuration with the profile named by --x402-profile.
+    pub fn load(args: &Args) -> Result<Self, X402Error> {
+        Self::load_profile(args, args.x402_profile.as_deref())
+    }
+
+    /// Load configuration, merging each setting from the first source that
+    /// sets it:
+    /// 1. CLI flags
+    /// 2. X402_* environment variables (including .env)
+    /// 3. .x402.toml in the current directory or the nearest parent
+    /// 4. ~/.x402/config
+    ///
+    /// In both config files, settings come from the `[profiles.<name>]`
+    /// section selected by `profile` (or X402_PROFILE), or from the top level
+    /// when no profile is selected.
+    ///
+    /// A private key and a keystore come from the same source, and a raw key
+    /// wins over a keystore from that source; likewise a keystore password
+    /// and password file, and a Solana secret key and keypair file. An
+    /// external signer (--x402-signer, X402_SIGNER, or `signer`) replaces the
+    /// private key and keystore when set.
+    ///
+    /// A project file can't set `signer`, `rpc_url`, `solana_rpc_url` or
+    /// `approve_cmd`, can only lower `limits.max_amount` and budgets, only
+    /// narrow policy allow lists, and can switch `defaults.confirm` and
+    /// `selection.funded_only` on but not off; anything else it sets there is
+    /// ignored with a warning. Policy deny lists from every source are combined.
+    pub fn load_profile(args: &Args, profile: Option<&str>) -> Result<Self, X402Error> {
+        let cli = Layer::from_args(args);
+        let env = Layer::from_env();
+        let (profile, profile_source) = match profile {
+            Some(name) => (Some(name.to_string()), Some(Source::Cli)),
+            None => match std::env::var("X402_PROFILE").ok().filter(|p| !p.is_empty()) {
+                Some(name) => (Some(name), Some(Source::Env)),
+                None => (None, None),
+            },
+        };
+
+        let mut layers = vec![(Source::Cli, cli), (Source::Env, env)];
+        let files: [(Option<PathBuf>, fn(PathBuf) -> Source); 2] = [
+            (Self::project_config_path(), Source::Project),
+            (Self::global_config_path(), Source::Global),
+        ];
+        let mut profile_found = false;
+        for (path, source) in files {
+            let Some(path) = path.filter(|p| p.exists()) else {
+                continue;
+            };
+            if let Some(layer) = Layer::from_file(&path, profile.as_deref())? {
+                layers.push((source(path), layer));
+                profile_found = true;
+            }
+        }
+        if let Some(ref name) = profile {
+            if !profile_found {
+                return Err(unknown_profile(name));
             }
         }
 
-        // Priority 6: ~/.x402/config
-        if let Some(config_path) = Self::global_config_path() {
-            if config_path.exists() {
-                return Self::load_from_file(&config_path);
+        let (mut config, ignored) = Layers {
+            layers,
+            sources: BTreeMap::new(),
+            ignored: Vec::new(),
+        }
+        .resolve();
+        if !args.x402_json {
+            for (key, path, reason) in ignored {
+                eprintln!(
+                    "Warning: ignoring {} from {}: {}",
+                    key,
+                    path.display(),
+                    reason
+                );
             }
         }
+        if let Some(source) = profile_source {
+            config.sources.insert("profile".to_string(), source);
+        }
+        config.profile = profile;
+        Ok(config)
+    }
 
-        // No credentials found
-        Ok(Config::default())
+    fn project_config_path() -> Option<PathBuf> {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG))
+            .find(|path| path.is_file())
     }
 
     fn global_config_path() -> Option<PathBuf> {
         dirs::home_dir().map(|h| h.join(".x402").join("config"))
     }
 
-    fn load_from_file(path: &PathBuf) -> Result<Self, X402Error> {
-        let content = fs::read_to_string(path)
-            .map_err(|e| X402Error::Config(format!("Failed to read config file: {}", e)))?;
+    /// Print the effective settings for `x402curl config show`, with secrets
+    /// masked. With `explain`, each value is followed by its source.
+    pub fn show(&self, explain: bool) {
+        let text = |value: &Option<String>| value.as_ref().map(|v| format!("{:?}", v));
+        let secret = |value: &Option<String>| {
+            value.as_ref().map(|v| {
+                if v.starts_with("keyring:") {
+                    format!("{:?}", v)
+                } else {
+                    "\"********\"".to_string()
+                }
+            })
+        };
+        let list = |values: &Vec<String>| (!values.is_empty()).then(|| format!("{:?}", values));
+
+        let mut entries: Vec<(String, Option<String>)> = [
+            ("profile", text(&self.profile)),
+            ("private_key", secret(&self.private_key)),
+            ("wallet_path", text(&self.wallet_path)),
+            ("wallet_password", secret(&self.wallet_password)),
+            ("wallet_password_file", text(&self.wallet_password_file)),
+            ("signer", text(&self.signer_url)),
+            ("solana_private_key", secret(&self.solana_private_key)),
+            ("solana_keypair_path", text(&self.solana_keypair_path)),
+            ("rpc_url", text(&self.rpc_url)),
+            ("solana_rpc_url", text(&self.solana_rpc_url)),
+            ("defaults.verbose", Some(self.verbose.to_string())),
+            ("defaults.confirm", Some(self.confirm.to_string())),
+            ("approve_cmd", text(&self.approve_cmd)),
+            ("limits.max_amount", text(&self.limits.max_amount)),
+            ("budget.daily", text(&self.budget.global.daily)),
+            ("budget.monthly", text(&self.budget.global.monthly)),
+            ("selection.networks", list(&self.selection.networks)),
+            ("selection.assets", list(&self.selection.assets)),
+            (
+                "selection.cheapest",
+                Some(self.selection.cheapest.to_string()),
+            ),
+            (
+                "selection.funded_only",
+                Some(self.selection.funded_only.to_string()),
+            ),
+            ("policy.allow_hosts", list(&self.policy.allow_hosts)),
+            ("policy.deny_hosts", list(&self.policy.deny_hosts)),
+            ("policy.allow_pay_to", list(&self.policy.allow_pay_to)),
+            ("policy.deny_pay_to", list(&self.policy.deny_pay_to)),
+            ("policy.allow_assets", list(&self.policy.allow_assets)),
+            ("policy.deny_assets", list(&self.policy.deny_assets)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        for (host, limits) in &self.budget.hosts {
+            entries.push((host_key(host, "daily"), text(&limits.daily)));
+            entries.push((host_key(host, "monthly"), text(&limits.monthly)));
+        }
+
+        for (key, value) in entries {
+            let Some(value) = value else {
+                continue;
+            };
+            if explain {
+                let source = self
+                    .sources
+                    .get(&key)
+                    .map_or_else(|| "default".to_string(), |s| s.to_string());
+                println!("{} = {}  # {}", key, value, source);
+            } else {
+                println!("{} = {}", key, value);
+            }
+        }
+    }
+
+    /// Whether an EVM private key, keystore or external signer is configured.
+    pub fn has_evm_credentials(&self) -> bool {
+        self.private_key.is_some() || self.wallet_path.is_some() || self.signer_url.is_some()
+    }
+
+    /// Whether a Solana secret key or keypair file is configured.
+    pub fn has_solana_credentials(&self) -> bool {
+        self.solana_private_key.is_some() || self.solana_keypair_path.is_some()
+    }
+
+    /// Build the Solana keypair, if Solana credentials are configured.
+    /// Priority: base58 secret key first, then JSON keypair file.
+    pub fn solana_keypair(&self) -> Result<Option<Keypair>, X402Error> {
+        let bytes = if let Some(ref key) = self.solana_private_key {
+            bs58::decode(key.trim())
+                .into_vec()
+                .map_err(|e| X402Error::Config(format!("Invalid Solana private key: {}", e)))?
+        } else if let Some(ref path) = self.solana_keypair_path {
+            let content = fs::read_to_string(path).map_err(|e| {
+                X402Error::Config(format!("Failed to read Solana keypair '{}': {}", path, e))
+            })?;
+            serde_json::from_str::<Vec<u8>>(&content).map_err(|e| {
+                X402Error::Config(format!("Invalid Solana keypair file '{}': {}", path, e))
+            })?
+        } else {
+            return Ok(None);
+        };
+
+        Keypair::try_from(bytes.as_slice())
+            .map(Some)
+            .map_err(|e| X402Error::Config(format!("Invalid Solana keypair: {}", e)))
+    }
+
+    /// Build the Solana keypair, failing if none is configured.
+    pub fn require_solana_keypair(&self) -> Result<Keypair, X402Error> {
+        self.solana_keypair()?.ok_or_else(|| {
+            X402Error::Config(
+                "No Solana credentials found. Set X402_SOLANA_PRIVATE_KEY, use \
+                 --x402-solana-key, or provide a keypair file with --x402-solana-keypair."
+                    .to_string(),
+            )
+        })
+    }
 
-        let config_file: ConfigFile = toml::from_str(&content)
-            .map_err(|e| X402Error::Config(format!("Failed to parse config file: {}", e)))?;
+    /// Build the EVM signer: the external signer if one is configured,
+    /// otherwise a local key from [`Config::require_signer`].
+    pub async fn require_evm_signer(&self) -> Result<EvmSigner, X402Error> {
+        match self.signer_url {
+            Some(ref url) => RemoteSigner::connect(url).await.map(EvmSigner::Remote),
+            None => self.require_signer().map(EvmSigner::Local),
+        }
+    }
 
-        Ok(Config {
-            private_key: config_file.private_key,
-            wallet_path: config_file.wallet_path,
-            wallet_password: config_file.wallet_password,
-            verbose: config_file.defaults.verbose,
-            confirm: config_file.defaults.confirm,
+    /// The keystore password: given inline, read from the password file, or
+    /// prompted for on the terminal when running interactively.
+    fn keystore_password(&self, wallet_path: &str) -> Result<String, X402Error> {
+        if let Some(ref password) = self.wallet_password {
+            return Ok(password.clone());
+        }
+        if let Some(ref file) = self.wallet_password_file {
+            return wallet::read_password_file(Path::new(file));
+        }
+        wallet::prompt_password(&format!("Password for {}: ", wallet_path))?.ok_or_else(|| {
+            X402Error::Config(
+                "Wallet keystore specified but no password provided. \
+                 Use --x402-wallet-password-file, set X402_WALLET_PASSWORD, \
+                 or run in a terminal to be prompted."
+                    .to_string(),
+            )
         })
     }
 
@@ -127,21 +782,13 @@ impl Config {
     /// Priority: private_key first, then wallet keystore.
     pub fn require_signer(&self) -> Result<PrivateKeySigner, X402Error> {
         if let Some(ref key) = self.private_key {
-            return key
+            return secrets::resolve(key)?
                 .parse()
                 .map_err(|e| X402Error::Config(format!("Invalid private key: {}", e)));
         }
 
         if let Some(ref wallet_path) = self.wallet_path {
-            let password = self.wallet_password.as_deref().ok_or_else(|| {
-                X402Error::Config(
-                    "Wallet keystore specified but no password provided. \
-                     Use --x402-wallet-password or set X402_WALLET_PASSWORD."
-                        .to_string(),
-                )
-            })?;
-
-            let path = Path::new(wallet_path);
+            let path = wallet::resolve_path(wallet_path);
             if !path.exists() {
                 return Err(X402Error::Config(format!(
                     "Wallet keystore file not found: {}",
@@ -149,7 +796,8 @@ impl Config {
                 )));
             }
 
-            return PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+            let password = self.keystore_password(wallet_path)?;
+            return PrivateKeySigner::decrypt_keystore(&path, password).map_err(|e| {
                 X402Error::Config(format!(
                     "Failed to decrypt keystore '{}': {}",
                     wallet_path, e
@@ -164,3 +812,317 @@ impl Config {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+private_key = "0xdefault"
+rpc_url = "https://default.example"
+
+[limits]
+max_amount = "0.10"
+
+[profiles.staging]
+wallet_path = "staging"
+rpc_url = "https://staging.example"
+
+[profiles.staging.selection]
+networks = ["base-sepolia"]
+"#;
+
+    fn write_config() -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), CONFIG).unwrap();
+        file
+    }
+
+    fn file_layer(profile: Option<&str>) -> Option<Layer> {
+        Layer::from_file(write_config().path(), profile).unwrap()
+    }
+
+    fn resolve(layers: Vec<(Source, Layer)>) -> Config {
+        Layers {
+            layers,
+            sources: BTreeMap::new(),
+            ignored: Vec::new(),
+        }
+        .resolve()
+        .0
+    }
+
+    #[test]
+    fn test_top_level_is_default_profile() {
+        let layer = file_layer(None).unwrap();
+        assert_eq!(layer.private_key.as_deref(), Some("0xdefault"));
+        assert_eq!(layer.rpc_url.as_deref(), Some("https://default.example"));
+        assert_eq!(layer.limits.max_amount.as_deref(), Some("0.10"));
+    }
+
+    #[test]
+    fn test_named_profile() {
+        let layer = file_layer(Some("staging")).unwrap();
+        assert_eq!(layer.private_key, None);
+        assert_eq!(layer.wallet_path.as_deref(), Some("staging"));
+        assert_eq!(layer.rpc_url.as_deref(), Some("https://staging.example"));
+        assert_eq!(layer.limits.max_amount, None);
+        assert_eq!(
+            layer.selection.networks,
+            Some(vec!["base-sepolia".to_string()])
+        );
+        assert!(file_layer(Some("prod")).is_none());
+    }
+
+    #[test]
+    fn test_settings_merge_field_by_field() {
+        let global = PathBuf::from("/home/agent/.x402/config");
+        let env = Layer {
+            private_key: Some("0xenv".to_string()),
+            ..Default::default()
+        };
+        let file = Layer {
+            private_key: Some("0xglobal".to_string()),
+            defaults: LayerDefaults {
+                verbose: Some(true),
+                confirm: None,
+            },
+            limits: Limits {
+                max_amount: Some("0.10".to_string()),
+            },
+            ..Default::default()
+        };
+        let config = resolve(vec![
+            (Source::Env, env),
+            (Source::Global(global.clone()), file),
+        ]);
+
+        assert_eq!(config.private_key.as_deref(), Some("0xenv"));
+        assert!(config.verbose);
+        assert!(!config.confirm);
+        assert_eq!(config.limits.max_amount.as_deref(), Some("0.10"));
+        assert_eq!(config.sources["private_key"], Source::Env);
+        assert_eq!(config.sources["defaults.verbose"], Source::Global(global));
+        assert!(!config.sources.contains_key("defaults.confirm"));
+    }
+
+    #[test]
+    fn test_keystore_overrides_lower_private_key() {
+        let cli = Layer {
+            wallet_path: Some("agent".to_string()),
+            ..Default::default()
+        };
+        let file = Layer {
+            private_key: Some("0xglobal".to_string()),
+            wallet_password: Some("secret".to_string()),
+            ..Default::default()
+        };
+        let config = resolve(vec![
+            (Source::Cli, cli),
+            (Source::Global(PathBuf::from("config")), file),
+        ]);
+
+        assert_eq!(config.private_key, None);
+        assert_eq!(config.wallet_path.as_deref(), Some("agent"));
+        assert_eq!(config.wallet_password.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_project_file_is_not_trusted() {
+        let project = Layer {
+            signer: Some("http://attacker.example".to_string()),
+            rpc_url: Some("https://project.example".to_string()),
+            limits: Limits {
+                max_amount: Some("5.00".to_string()),
+            },
+            ..Default::default()
+        };
+        let global = Layer {
+            rpc_url: Some("https://global.example".to_string()),
+            limits: Limits {
+                max_amount: Some("0.10".to_string()),
+            },
+            ..Default::default()
+        };
+        let (config, ignored) = Layers {
+            layers: vec![
+                (Source::Project(PathBuf::from(".x402.toml")), project),
+                (Source::Global(PathBuf::from("config")), global),
+            ],
+            sources: BTreeMap::new(),
+            ignored: Vec::new(),
+        }
+        .resolve();
+
+        assert_eq!(config.signer_url, None);
+        assert_eq!(config.rpc_url.as_deref(), Some("https://global.example"));
+        assert_eq!(config.limits.max_amount.as_deref(), Some("0.10"));
+        let keys: Vec<&str> = ignored.iter().map(|(key, _, _)| key.as_str()).collect();
+        assert_eq!(keys, ["signer", "rpc_url", "limits.max_amount"]);
+    }
+
+    #[test]
+    fn test_project_file_cannot_switch_off_safeguards() {
+        let project = |enabled: bool| Layer {
+            defaults: LayerDefaults {
+                confirm: Some(enabled),
+                ..Default::default()
+            },
+            selection: LayerSelection {
+                funded_only: Some(enabled),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let layers = |project: Layer, global: Option<bool>| {
+            vec![
+                (Source::Project(PathBuf::from(".x402.toml")), project),
+                (
+                    Source::Global(PathBuf::from("config")),
+                    Layer {
+                        defaults: LayerDefaults {
+                            confirm: global,
+                            ..Default::default()
+                        },
+                        selection: LayerSelection {
+                            funded_only: global,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                ),
+            ]
+        };
+
+        let switched_off = resolve(layers(project(false), Some(true)));
+        assert!(switched_off.confirm);
+        assert!(switched_off.selection.funded_only);
+
+        let switched_on = resolve(layers(project(true), None));
+        assert!(switched_on.confirm);
+        assert!(switched_on.selection.funded_only);
+        let unchanged = resolve(layers(project(false), Some(false)));
+        assert!(!unchanged.confirm);
+    }
+
+    #[test]
+    fn test_project_file_cannot_set_approve_cmd() {
+        let project = Layer {
+            approve_cmd: Some("./approve-everything".to_string()),
+            ..Default::default()
+        };
+        let config = resolve(vec![(
+            Source::Project(PathBuf::from(".x402.toml")),
+            project,
+        )]);
+        assert_eq!(config.approve_cmd, None);
+    }
+
+    #[test]
+    fn test_policy_lists_merge() {
+        let policy = |allow: &[&str], deny: &[&str]| LayerPolicy {
+            allow_hosts: Some(allow.iter().map(|h| h.to_string()).collect()),
+            deny_hosts: Some(deny.iter().map(|h| h.to_string()).collect()),
+            ..Default::default()
+        };
+        let layers = |project: LayerPolicy| {
+            vec![
+                (
+                    Source::Project(PathBuf::from(".x402.toml")),
+                    Layer {
+                        policy: project,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    Source::Global(PathBuf::from("config")),
+                    Layer {
+                        policy: policy(
+                            &["api.example.com", "*.trusted.dev"],
+                            &["evil.trusted.dev"],
+                        ),
+                        ..Default::default()
+                    },
+                ),
+            ]
+        };
+
+        // Deny lists add up; an allow list can't be widened
+        let widened = resolve(layers(policy(&["*"], &[])));
+        assert_eq!(
+            widened.policy.allow_hosts,
+            ["api.example.com", "*.trusted.dev"]
+        );
+        assert_eq!(widened.policy.deny_hosts, ["evil.trusted.dev"]);
+        let emptied = resolve(layers(policy(&[], &["api.example.com"])));
+        assert_eq!(
+            emptied.policy.allow_hosts,
+            ["api.example.com", "*.trusted.dev"]
+        );
+        assert_eq!(
+            emptied.policy.deny_hosts,
+            ["api.example.com", "evil.trusted.dev"]
+        );
+
+        // but it can be narrowed
+        let narrowed = resolve(layers(policy(&["api.example.com"], &[])));
+        assert_eq!(narrowed.policy.allow_hosts, ["api.example.com"]);
+    }
+
+    #[test]
+    fn test_project_file_can_lower_caps() {
+        let project = Layer {
+            limits: Limits {
+                max_amount: Some("0.01".to_string()),
+            },
+            ..Default::default()
+        };
+        let global = Layer {
+            limits: Limits {
+                max_amount: Some("0.10".to_string()),
+            },
+            ..Default::default()
+        };
+        let config = resolve(vec![
+            (Source::Project(PathBuf::from(".x402.toml")), project),
+            (Source::Global(PathBuf::from("config")), global),
+        ]);
+        assert_eq!(config.limits.max_amount.as_deref(), Some("0.01"));
+    }
+
+    #[test]
+    fn test_host_budgets_merge() {
+        let budget = |host: &str, daily: &str| Budget {
+            global: BudgetLimits::default(),
+            hosts: BTreeMap::from([(
+                host.to_string(),
+                BudgetLimits {
+                    daily: Some(daily.to_string()),
+                    monthly: None,
+                },
+            )]),
+        };
+        let project = Layer {
+            budget: budget("api.example.com", "1.00"),
+            ..Default::default()
+        };
+        let global = Layer {
+            budget: budget("other.example.com", "2.00"),
+            ..Default::default()
+        };
+        let config = resolve(vec![
+            (Source::Project(PathBuf::from(".x402.toml")), project),
+            (Source::Global(PathBuf::from("config")), global),
+        ]);
+
+        assert_eq!(config.budget.hosts.len(), 2);
+        assert_eq!(
+            config.budget.hosts["api.example.com"].daily.as_deref(),
+            Some("1.00")
+        );
+        assert_eq!(
+            config.sources[&host_key("other.example.com", "daily")],
+            Source::Global(PathBuf::from("config"))
+        );
+    }
+}
@@ -10,12 +10,11 @@ pub enum X402Error {
     Network(#[from] reqwest::Error),
 
     #[error("Middleware error: {0}")]
-    Middleware(#[from] reqwest_middleware::Error),
+    Middleware(reqwest_middleware::Error),
 
     #[error("RPC error: {0}")]
     Rpc(String),
 
-    #[allow(dead_code)]
     #[error("Payment error: {0}")]
     Payment(String),
 
@@ -26,6 +25,21 @@ pub enum X402Error {
     Config(String),
 }
 
+impl From<reqwest_middleware::Error> for X402Error {
+    /// Errors raised by our own middleware (e.g. the payment guard) travel
+    /// through reqwest-middleware as `anyhow` errors; unwrap them so they
+    /// keep their original exit code.
+    fn from(err: reqwest_middleware::Error) -> Self {
+        match err {
+            reqwest_middleware::Error::Middleware(inner) => match inner.downcast::<X402Error>() {
+                Ok(e) => e,
+                Err(inner) => X402Error::Middleware(reqwest_middleware::Error::Middleware(inner)),
+            },
+            other => X402Error::Middleware(other),
+        }
+    }
+}
+
 impl X402Error {
     pub fn exit_code(&self) -> ExitCode {
         match self {
@@ -0,0 +1,507 @@
+use crate::balance::{self, parse_token_amount};
+use crate::budget::{self, Window};
+use crate::chains;
+use crate::config::{Budget, Policy, Selection};
+use crate::error::X402Error;
+use crate::ledger;
+use crate::payment::{
+    format_amount, PaymentOption, PaymentRequired, PAYMENT_SIGNATURE_HEADER, X_PAYMENT_HEADER,
+};
+use crate::policy;
+use crate::selection;
+use http::Extensions;
+use reqwest::header::HeaderMap;
+use reqwest::{Request, Response, ResponseBuilderExt, StatusCode, Url};
+use reqwest_middleware::{Middleware, Next};
+use std::sync::{Arc, Mutex};
+
+/// Middleware that inspects 402 responses before the x402 client signs.
+///
+/// It must be registered after (i.e. inside) the x402 payment middleware so
+/// that it sees the unpaid response first. Payment options that the payment
+/// policy rules out, that violate the configured limits, or that the wallet
+/// can't afford, are removed from the response; if none remain the request
+/// fails with [`X402Error::Payment`] and nothing is signed.
+///
+/// Requirements are decoded with the SDK's types, as the x402 client decodes
+/// them. While any limit, policy or confirmation applies, a 402 response
+/// whose requirements can't all be decoded is refused rather than passed on
+/// unchecked.
+///
+/// The remaining options are then ordered by the selection policy, so the
+/// x402 client pays with the preferred one, and passed to the confirmation
+/// callback, if any, which can still decline the payment.
+///
+/// When the x402 client retries with a signed payment, the guard records
+/// which option was paid so the caller can log it.
+pub struct PaymentGuard {
+    settings: GuardSettings,
+    /// Options passed on to the x402 client from the last 402 response
+    offered: Mutex<Vec<PaymentOption>>,
+    /// The option the x402 client signed a payment for
+    paid: Arc<Mutex<Option<PaymentOption>>>,
+    /// The 402 response the guard refused to pay
+    refused: Arc<Mutex<Option<Refused>>>,
+}
+
+/// Asked before signing, with the URL being paid for and the options left
+/// after all checks, first one preferred. Returns whether to pay; an error
+/// refuses the payment and fails the request.
+pub type Confirm = Box<dyn Fn(&str, &PaymentRequired) -> Result<bool, X402Error> + Send + Sync>;
+
+/// A 402 response that was not paid, kept so the caller can still show
+/// what the server asked for.
+pub struct Refused {
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Declined at confirmation, rather than failed with an error
+    pub cancelled: bool,
+}
+
+/// Limits the guard enforces before a payment is signed.
+#[derive(Default)]
+pub struct GuardSettings {
+    /// Maximum amount per payment, in token units (e.g. "0.10")
+    pub max_amount: Option<String>,
+    /// Rolling budgets checked against the ledger
+    pub budget: Budget,
+    /// Preferred networks and assets when several options are accepted
+    pub selection: Selection,
+    /// Hosts, recipients and assets that may be paid
+    pub policy: Policy,
+    /// Wallet address whose balance is checked before signing; no check if unset
+    pub payer: Option<String>,
+    /// RPC endpoint for the balance check on its own chain, instead of that
+    /// network's public RPC
+    pub rpc_url: Option<String>,
+    /// Solana wallet address, checked for options on Solana networks
+    pub solana_payer: Option<String>,
+    /// Solana RPC endpoint, instead of the cluster's public RPC
+    pub solana_rpc_url: Option<String>,
+    /// Asked before each payment is signed; payments go ahead if unset
+    pub confirm: Option<Confirm>,
+    /// Print remaining budget to stderr
+    pub verbose: bool,
+}
+
+impl PaymentGuard {
+    pub fn new(settings: GuardSettings) -> Self {
+        PaymentGuard {
+            settings,
+            offered: Mutex::new(Vec::new()),
+            paid: Arc::new(Mutex::new(None)),
+            refused: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Shared handle to the payment made through this guard, if any.
+    pub fn paid(&self) -> Arc<Mutex<Option<PaymentOption>>> {
+        self.paid.clone()
+    }
+
+    /// Shared handle to the 402 response denied by the payment policy or
+    /// declined at confirmation, if any.
+    pub fn refused(&self) -> Arc<Mutex<Option<Refused>>> {
+        self.refused.clone()
+    }
+
+    fn refuse(
+        &self,
+        headers: HeaderMap,
+        body: Vec<u8>,
+        cancelled: bool,
+        error: X402Error,
+    ) -> reqwest_middleware::Error {
+        *self.refused.lock().unwrap() = Some(Refused {
+            headers,
+            body,
+            cancelled,
+        });
+        reqwest_middleware::Error::Middleware(error.into())
+    }
+
+    /// Whether any limit, policy or confirmation applies to paying `host`.
+    fn restricts(&self, host: Option<&str>) -> bool {
+        let settings = &self.settings;
+        settings.max_amount.is_some()
+            || !budget::windows(&settings.budget, host).is_empty()
+            || !settings.policy.is_empty()
+            || settings.confirm.is_some()
+    }
+
+    fn within_max_amount(option: &PaymentOption, max_amount: &str) -> bool {
+        // Unknown assets can't be compared against a cap, so they're rejected
+        option
+            .decimals()
+            .and_then(|decimals| parse_token_amount(max_amount, decimals).ok())
+            .is_some_and(|max| option.amount <= max)
+    }
+
+    fn within_budget(option: &PaymentOption, remaining: u128) -> bool {
+        budget::to_budget_units(option.amount, &option.network, &option.asset)
+            .is_some_and(|cost| cost <= remaining)
+    }
+
+    /// Budget windows for a request to `host`, with spend from the ledger.
+    fn budget_windows(&self, host: Option<&str>) -> Result<Vec<Window>, X402Error> {
+        let mut windows = budget::windows(&self.settings.budget, host);
+        if !windows.is_empty() {
+            budget::tally(&mut windows, &ledger::load()?, chrono::Utc::now());
+        }
+        Ok(windows)
+    }
+
+    fn check(&self, required: &mut PaymentRequired, windows: &[Window]) -> Result<(), X402Error> {
+        let requested = required
+            .options
+            .first()
+            .map(PaymentOption::describe)
+            .unwrap_or_default();
+
+        if let Some(ref max_amount) = self.settings.max_amount {
+            required.retain(|option| Self::within_max_amount(option, max_amount));
+            if required.options.is_empty() {
+                return Err(X402Error::Payment(format!(
+                    "Payment of {} exceeds maximum amount {}",
+                    requested, max_amount
+                )));
+            }
+        }
+
+        // The tightest window decides what can still be spent
+        if let Some(tightest) = windows.iter().min_by_key(|w| w.remaining()) {
+            if self.settings.verbose {
+                for window in windows {
+                    eprintln!("* {}", window.describe());
+                }
+            }
+            required.retain(|option| Self::within_budget(option, tightest.remaining()));
+            if required.options.is_empty() {
+                return Err(X402Error::Payment(format!(
+                    "Payment of {} would exceed the {}",
+                    requested,
+                    tightest.describe()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wallet balance of an option's asset, or `None` if the network is
+    /// unknown or no wallet is configured for it. The RPC override is only
+    /// queried for options on its chain, `rpc_chain_id`.
+    async fn balance_of(
+        &self,
+        client: &reqwest::Client,
+        option: &PaymentOption,
+        rpc_chain_id: Option<u64>,
+    ) -> Option<Result<u128, X402Error>> {
+        if let Some(chain) = chains::solana_by_network(&option.network) {
+            let owner = self.settings.solana_payer.as_deref()?;
+            let rpc_url = self
+                .settings
+                .solana_rpc_url
+                .as_deref()
+                .unwrap_or(chain.rpc_url);
+            return Some(balance::spl_token_balance(client, rpc_url, &option.asset, owner).await);
+        }
+
+        let payer = self.settings.payer.as_deref()?;
+        let chain = chains::by_network(&option.network)?;
+        let rpc_url = match self.settings.rpc_url {
+            Some(ref url) if rpc_chain_id == Some(chain.id) => url.as_str(),
+            _ => chain.rpc_url,
+        };
+        Some(balance::token_balance(client, rpc_url, &option.asset, payer).await)
+    }
+
+    /// Wallet balance of each option's asset, or `None` where it couldn't
+    /// be queried (unknown network, RPC failure).
+    async fn balances(&self, required: &PaymentRequired) -> Vec<Option<u128>> {
+        let client = reqwest::Client::new();
+        let evm_options = required
+            .options
+            .iter()
+            .any(|option| chains::by_network(&option.network).is_some());
+        let rpc_chain_id = match self.settings.rpc_url {
+            Some(ref url) if evm_options => balance::chain_id(&client, url).await.ok(),
+            _ => None,
+        };
+
+        let mut balances = Vec::with_capacity(required.options.len());
+        for option in &required.options {
+            let balance = match self.balance_of(&client, option, rpc_chain_id).await {
+                Some(Ok(balance)) => Some(balance),
+                Some(Err(e)) => {
+                    if self.settings.verbose {
+                        eprintln!("* Balance check skipped on {}: {}", option.network, e);
+                    }
+                    None
+                }
+                None => None,
+            };
+            balances.push(balance);
+        }
+        balances
+    }
+
+    /// Drop options the wallet can't afford. Options whose balance is
+    /// unknown are kept and left for the facilitator to verify, unless
+    /// `funded_only` is set.
+    fn check_funds(
+        required: &mut PaymentRequired,
+        balances: &[Option<u128>],
+        funded_only: bool,
+    ) -> Result<(), X402Error> {
+        let shortfall = required
+            .options
+            .iter()
+            .zip(balances)
+            .find_map(|(option, balance)| {
+                balance.filter(|b| *b < option.amount).map(|b| {
+                    format!(
+                        "insufficient funds: need {}, have {}",
+                        option.describe(),
+                        format_amount(b, &option.network, &option.asset)
+                    )
+                })
+            });
+
+        let mut funded = required
+            .options
+            .iter()
+            .zip(balances)
+            .map(|(option, balance)| balance.map_or(!funded_only, |b| b >= option.amount))
+            .collect::<Vec<_>>()
+            .into_iter();
+        required.retain(|_| funded.next().unwrap_or(!funded_only));
+
+        if required.options.is_empty() {
+            return Err(X402Error::Payment(shortfall.unwrap_or_else(|| {
+                "no payment option on a network where the wallet balance could be verified"
+                    .to_string()
+            })));
+        }
+        Ok(())
+    }
+}
+
+/// A response with the given parts, from `url` so that `-w` can still report
+/// the effective URL.
+fn rebuild_response(url: &Url, status: StatusCode, headers: HeaderMap, body: Vec<u8>) -> Response {
+    let mut response = http::Response::builder()
+        .status(status)
+        .url(url.clone())
+        .body(body)
+        .expect("a status code and a URL make a valid response");
+    *response.headers_mut() = headers;
+    Response::from(response)
+}
+
+#[async_trait::async_trait]
+impl Middleware for PaymentGuard {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let payment = req
+            .headers()
+            .get(PAYMENT_SIGNATURE_HEADER)
+            .or_else(|| req.headers().get(X_PAYMENT_HEADER))
+            .cloned();
+        let response = next.run(req, extensions).await?;
+        // With -L the client follows redirects below this middleware, so the
+        // server asking to be paid is the one that sent the final response
+        let url = response.url().clone();
+        let host = url.host_str().map(|h| h.to_string());
+
+        // Paid retry: remember what was signed and pass the response through
+        if let Some(payment) = payment {
+            let offered = std::mem::take(&mut *self.offered.lock().unwrap());
+            *self.paid.lock().unwrap() = PaymentOption::from_payment_header(&payment, &offered);
+            return Ok(response);
+        }
+
+        if response.status() != StatusCode::PAYMENT_REQUIRED {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?.to_vec();
+
+        let mut required = match PaymentRequired::decode(&headers, &body) {
+            Ok(Some(required)) => required,
+            // Requirements that can't be checked can't be let through
+            Err(e) if self.restricts(host.as_deref()) => {
+                return Err(self.refuse(headers, body, false, e))
+            }
+            _ => return Ok(rebuild_response(&url, status, headers, body)),
+        };
+
+        if let Err(e) = policy::apply(&self.settings.policy, host.as_deref(), &mut required) {
+            if let Err(log_error) = policy::log_violation(url.as_str(), &e) {
+                eprintln!("Warning: policy violation not logged: {}", log_error);
+            }
+            return Err(self.refuse(headers, body, false, e));
+        }
+        self.budget_windows(host.as_deref())
+            .and_then(|windows| self.check(&mut required, &windows))
+            .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+        let balances = self.balances(&required).await;
+        Self::check_funds(
+            &mut required,
+            &balances,
+            self.settings.selection.funded_only,
+        )
+        .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+        selection::sort(&self.settings.selection, &mut required);
+
+        if let Some(ref confirm) = self.settings.confirm {
+            match confirm(url.as_str(), &required) {
+                Ok(true) => {}
+                Ok(false) => {
+                    let cancelled = X402Error::Payment("payment cancelled".to_string());
+                    return Err(self.refuse(headers, body, true, cancelled));
+                }
+                Err(e) => return Err(self.refuse(headers, body, false, e)),
+            }
+        }
+        *self.offered.lock().unwrap() = required.options.clone();
+
+        let (headers, body) = required.encode(headers, body);
+        Ok(rebuild_response(&url, status, headers, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BudgetLimits;
+    use crate::payment::fixtures::{v1_required, USDC};
+
+    fn max_amount(amount: &str) -> GuardSettings {
+        GuardSettings {
+            max_amount: Some(amount.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn daily_budget(amount: &str) -> Budget {
+        Budget {
+            global: BudgetLimits {
+                daily: Some(amount.to_string()),
+                monthly: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_no_limit_allows_payment() {
+        let guard = PaymentGuard::new(GuardSettings::default());
+        assert!(guard.check(&mut v1_required("10000", USDC), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_max_amount_allows_payment_at_cap() {
+        let guard = PaymentGuard::new(max_amount("0.01"));
+        assert!(guard.check(&mut v1_required("10000", USDC), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_max_amount_rejects_payment_above_cap() {
+        let guard = PaymentGuard::new(max_amount("0.001"));
+        let err = guard
+            .check(&mut v1_required("10000", USDC), &[])
+            .unwrap_err();
+        assert!(matches!(err, X402Error::Payment(_)));
+        assert!(err.to_string().contains("0.010000 USDC"));
+    }
+
+    #[test]
+    fn test_max_amount_rejects_unknown_asset() {
+        let guard = PaymentGuard::new(max_amount("100"));
+        let asset = "0x0000000000000000000000000000000000000001";
+        assert!(guard.check(&mut v1_required("1", asset), &[]).is_err());
+    }
+
+    #[test]
+    fn test_budget_allows_payment_within_remaining() {
+        let budget = daily_budget("1.00");
+        let guard = PaymentGuard::new(GuardSettings {
+            budget: budget.clone(),
+            ..Default::default()
+        });
+        let windows = budget::windows(&budget, None);
+        assert!(guard
+            .check(&mut v1_required("10000", USDC), &windows)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_budget_rejects_payment_over_remaining() {
+        let budget = daily_budget("0.005");
+        let guard = PaymentGuard::new(GuardSettings {
+            budget: budget.clone(),
+            ..Default::default()
+        });
+        let windows = budget::windows(&budget, None);
+        let err = guard
+            .check(&mut v1_required("10000", USDC), &windows)
+            .unwrap_err();
+        assert!(matches!(err, X402Error::Payment(_)));
+        assert!(err
+            .to_string()
+            .contains("would exceed the daily budget: 0.005 of 0.005 remaining"));
+    }
+
+    #[test]
+    fn test_funds_allow_exact_balance() {
+        let mut required = v1_required("10000", USDC);
+        assert!(PaymentGuard::check_funds(&mut required, &[Some(10_000)], false).is_ok());
+        assert_eq!(required.options.len(), 1);
+    }
+
+    #[test]
+    fn test_funds_unknown_balance_is_kept() {
+        let mut required = v1_required("10000", USDC);
+        assert!(PaymentGuard::check_funds(&mut required, &[None], false).is_ok());
+        assert_eq!(required.options.len(), 1);
+    }
+
+    #[test]
+    fn test_funded_only_drops_unknown_balance() {
+        let err =
+            PaymentGuard::check_funds(&mut v1_required("10000", USDC), &[None], true).unwrap_err();
+        assert!(matches!(err, X402Error::Payment(_)));
+        assert!(err.to_string().contains("balance could be verified"));
+    }
+
+    #[test]
+    fn test_funds_rejects_underfunded_wallet() {
+        let err = PaymentGuard::check_funds(&mut v1_required("10000", USDC), &[Some(2_500)], false)
+            .unwrap_err();
+        assert!(matches!(err, X402Error::Payment(_)));
+        assert_eq!(
+            err.to_string(),
+            "Payment error: insufficient funds: need 0.010000 USDC on base-sepolia, have 0.002500 USDC"
+        );
+    }
+
+    #[test]
+    fn test_rebuild_response_keeps_url() {
+        let url = Url::parse("https://api.example.com/paid").unwrap();
+        let response = rebuild_response(
+            &url,
+            StatusCode::PAYMENT_REQUIRED,
+            HeaderMap::new(),
+            b"{}".to_vec(),
+        );
+        assert_eq!(response.url(), &url);
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::error::X402Error;
+use crate::payment::format_amount;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// One signed x402 payment, stored as a line of JSON in the ledger file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LedgerEntry {
+    pub timestamp: DateTime<Utc>,
+    pub url: String,
+    pub method: String,
+    pub scheme: String,
+    pub network: String,
+    pub asset: String,
+    /// Amount in the asset's atomic units
+    pub amount: String,
+    pub pay_to: String,
+    pub payer: String,
+    pub status: u16,
+    /// Settlement transaction hash from the payment-response header
+    pub transaction: Option<String>,
+}
+
+/// Filters for listing the ledger.
+#[derive(Debug, Default)]
+pub struct LedgerFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub host: Option<String>,
+}
+
+impl LedgerEntry {
+    pub fn host(&self) -> Option<String> {
+        reqwest::Url::parse(&self.url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+    }
+}
+
+impl LedgerFilter {
+    /// Build a filter from the `--x402-ledger-*` flags.
+    pub fn from_args(
+        since: Option<&str>,
+        until: Option<&str>,
+        host: Option<&str>,
+    ) -> Result<Self, X402Error> {
+        Ok(LedgerFilter {
+            since: since.map(|s| parse_date(s, false)).transpose()?,
+            until: until.map(|s| parse_date(s, true)).transpose()?,
+            host: host.map(|h| h.to_string()),
+        })
+    }
+
+    fn matches(&self, entry: &LedgerEntry) -> bool {
+        if self.since.is_some_and(|since| entry.timestamp < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| entry.timestamp >= until) {
+            return false;
+        }
+        match self.host {
+            Some(ref host) => entry.host().is_some_and(|h| h.eq_ignore_ascii_case(host)),
+            None => true,
+        }
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date or an RFC 3339 timestamp.
+/// With `end_of_day`, a plain date covers the whole day (used for `until`).
+fn parse_date(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, X402Error> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        X402Error::General(format!(
+            "Invalid date: {} (expected YYYY-MM-DD or RFC 3339)",
+            value
+        ))
+    })?;
+    let start = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default());
+    Ok(if end_of_day {
+        start + Duration::days(1)
+    } else {
+        start
+    })
+}
+
+fn ledger_path() -> Result<PathBuf, X402Error> {
+    dirs::home_dir()
+        .map(|h| h.join(".x402").join("ledger.jsonl"))
+        .ok_or_else(|| X402Error::General("Cannot determine home directory".to_string()))
+}
+
+/// Append a payment to the ledger file.
+pub fn append(entry: &LedgerEntry) -> Result<(), X402Error> {
+    let path = ledger_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| X402Error::General(format!("Failed to create ledger directory: {}", e)))?;
+    }
+
+    let line = serde_json::to_string(entry)
+        .map_err(|e| X402Error::General(format!("Failed to encode ledger entry: {}", e)))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| X402Error::General(format!("Failed to open ledger file: {}", e)))?;
+    writeln!(file, "{}", line)
+        .map_err(|e| X402Error::General(format!("Failed to write ledger file: {}", e)))
+}
+
+/// Read all payments from the ledger file. A missing ledger is empty.
+pub fn load() -> Result<Vec<LedgerEntry>, X402Error> {
+    let path = ledger_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| X402Error::General(format!("Failed to read ledger file: {}", e)))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                X402Error::General(format!("Invalid ledger entry on line {}: {}", i + 1, e))
+            })
+        })
+        .collect()
+}
+
+/// Sum atomic amounts per (network, asset), saturating at `u128::MAX`.
+fn totals<'a>(entries: &[&'a LedgerEntry]) -> BTreeMap<(&'a str, &'a str), (u128, usize)> {
+    let mut totals = BTreeMap::new();
+    for &entry in entries {
+        let total = totals
+            .entry((entry.network.as_str(), entry.asset.as_str()))
+            .or_insert((0u128, 0usize));
+        total.0 = total
+            .0
+            .saturating_add(entry.amount.parse::<u128>().unwrap_or(0));
+        total.1 += 1;
+    }
+    totals
+}
+
+/// List the ledger entries matching `filter`, followed by totals per asset.
+pub fn print_ledger(filter: &LedgerFilter) -> Result<(), X402Error> {
+    let entries = load()?;
+    let matching: Vec<&LedgerEntry> = entries.iter().filter(|e| filter.matches(e)).collect();
+
+    if matching.is_empty() {
+        println!("No payments recorded.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<20}  {:<20}  {:<14}  {:<6}  {:<66}  URL",
+        "TIMESTAMP", "AMOUNT", "NETWORK", "STATUS", "TRANSACTION"
+    );
+    for entry in &matching {
+        let amount = entry.amount.parse::<u128>().unwrap_or(0);
+        println!(
+            "{:<20}  {:<20}  {:<14}  {:<6}  {:<66}  {} {}",
+            entry.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+            format_amount(amount, &entry.network, &entry.asset),
+            entry.network,
+            entry.status,
+            entry.transaction.as_deref().unwrap_or("-"),
+            entry.method,
+            entry.url
+        );
+    }
+
+    println!();
+    for ((network, asset), (amount, count)) in totals(&matching) {
+        println!(
+            "Total:    {} on {} ({} payment{})",
+            format_amount(amount, network, asset),
+            network,
+            count,
+            if count == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: &str, url: &str, amount: &str) -> LedgerEntry {
+        LedgerEntry {
+            timestamp: parse_date(timestamp, false).unwrap(),
+            url: url.to_string(),
+            method: "POST".to_string(),
+            scheme: "exact".to_string(),
+            network: "base-sepolia".to_string(),
+            asset: "0x036CbD53842c5426634e7929541eC2318f3dCF7e".to_string(),
+            amount: amount.to_string(),
+            pay_to: "0x209693Bc6afc0C5328bA36FaF03C514EF312287C".to_string(),
+            payer: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string(),
+            status: 200,
+            transaction: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_date_plain_and_rfc3339() {
+        assert_eq!(
+            parse_date("2026-10-01", false).unwrap().to_rfc3339(),
+            "2026-10-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2026-10-01", true).unwrap().to_rfc3339(),
+            "2026-10-02T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2026-10-01T12:30:00+02:00", true)
+                .unwrap()
+                .to_rfc3339(),
+            "2026-10-01T10:30:00+00:00"
+        );
+        assert!(parse_date("yesterday", false).is_err());
+    }
+
+    #[test]
+    fn test_filter_by_date_range() {
+        let filter = LedgerFilter::from_args(Some("2026-10-01"), Some("2026-10-01"), None).unwrap();
+        assert!(!filter.matches(&entry("2026-09-30T23:59:59Z", "https://a.com/x", "1")));
+        assert!(filter.matches(&entry("2026-10-01T00:00:00Z", "https://a.com/x", "1")));
+        assert!(filter.matches(&entry("2026-10-01T23:59:59Z", "https://a.com/x", "1")));
+        assert!(!filter.matches(&entry("2026-10-02T00:00:00Z", "https://a.com/x", "1")));
+    }
+
+    #[test]
+    fn test_filter_by_host() {
+        let filter = LedgerFilter::from_args(None, None, Some("api.example.com")).unwrap();
+        assert!(filter.matches(&entry("2026-10-01", "https://api.example.com/ocr", "1")));
+        assert!(!filter.matches(&entry("2026-10-01", "https://other.com/ocr", "1")));
+    }
+
+    #[test]
+    fn test_totals_per_asset() {
+        let a = entry("2026-10-01", "https://a.com", "10000");
+        let b = entry("2026-10-02", "https://b.com", "5000");
+        let totals = totals(&[&a, &b]);
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals.values().next(), Some(&(15_000, 2)));
+    }
+
+    #[test]
+    fn test_totals_saturate() {
+        let max = u128::MAX.to_string();
+        let a = entry("2026-10-01", "https://a.com", &max);
+        let b = entry("2026-10-02", "https://b.com", "1");
+        let totals = totals(&[&a, &b]);
+        assert_eq!(totals.values().next(), Some(&(u128::MAX, 2)));
+    }
+}
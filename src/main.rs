@@ -1,86 +1,228 @@
+mod approve;
 mod balance;
+mod body;
+mod budget;
+mod chains;
 mod cli;
+mod cluster;
 mod config;
 mod error;
+mod guard;
+mod ledger;
 mod output;
+mod payment;
+mod policy;
 mod request;
-
-use cli::Args;
+mod secrets;
+mod selection;
+mod signer;
+mod sse;
+mod wallet;
+mod write_out;
+
+use body::StreamBody;
+use cli::{Args, Command, ConfigCommand};
+use cluster::Cluster;
 use config::Config;
 use error::X402Error;
-use output::handle_response;
+use guard::{GuardSettings, PaymentGuard};
+use ledger::{LedgerEntry, LedgerFilter};
+use output::{handle_response, write_receipt, JsonReport, ResponseSettings};
+use payment::{PaymentRequired, Settlement};
 use request::RequestConfig;
+use reqwest::header::HeaderMap;
 use reqwest_middleware::ClientWithMiddleware;
-use std::io::{self, Write};
+use signer::EvmSigner;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_signer::Signer;
+use std::io::{self, BufRead, Write};
 use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::Instant;
+use write_out::{write_out, Transfer};
 use x402_chain_eip155::v1_eip155_exact::client::V1Eip155ExactClient;
 use x402_chain_eip155::v2_eip155_exact::client::V2Eip155ExactClient;
-use x402_reqwest::{ReqwestWithPayments, ReqwestWithPaymentsBuild, X402Client};
+use x402_chain_solana::v1_solana_exact::client::V1SolanaExactClient;
+use x402_chain_solana::v2_solana_exact::client::V2SolanaExactClient;
+use x402_reqwest::X402Client;
+
+/// Print each accepted payment option, one per line.
+fn print_payment_options(required: &PaymentRequired) {
+    for (i, option) in required.options.iter().enumerate() {
+        eprintln!(
+            "  [{}] {} {} on {} to {} ({})",
+            i + 1,
+            option.display_amount(),
+            option.symbol(),
+            payment::network_name(&option.network),
+            option.pay_to,
+            option.scheme
+        );
+    }
+}
+
+/// The controlling terminal, for prompting when stdin is in use.
+fn terminal() -> io::Result<std::fs::File> {
+    #[cfg(windows)]
+    let path = "CONIN$";
+    #[cfg(not(windows))]
+    let path = "/dev/tty";
+    std::fs::File::open(path)
+}
+
+/// Ask whether to pay. The answer is read from stdin, or from the terminal
+/// if stdin carries the request body or the `-w` format.
+fn prompt_confirmation(required: &PaymentRequired, stdin_taken: bool) -> Result<bool, X402Error> {
+    let mut answers: Box<dyn BufRead> = if stdin_taken {
+        let tty = terminal().map_err(|e| {
+            X402Error::Config(format!(
+                "--confirm needs a terminal when stdin is already in use ({}); \
+                 use --x402-approve-cmd instead",
+                e
+            ))
+        })?;
+        Box::new(io::BufReader::new(tty))
+    } else {
+        Box::new(io::stdin().lock())
+    };
 
-fn prompt_confirmation(amount: &str, recipient: &str) -> Result<bool, X402Error> {
-    eprint!(
-        "Payment required: {}\nRecipient: {}\nProceed? [y/N] ",
-        amount, recipient
-    );
+    eprintln!("Payment required (x402 v{}):", required.x402_version);
+    print_payment_options(required);
+    eprint!("Proceed? [y/N] ");
     io::stderr()
         .flush()
         .map_err(|e| X402Error::General(e.to_string()))?;
 
     let mut input = String::new();
-    io::stdin()
+    answers
         .read_line(&mut input)
         .map_err(|e| X402Error::General(e.to_string()))?;
 
     Ok(input.trim().eq_ignore_ascii_case("y") || input.trim().eq_ignore_ascii_case("yes"))
 }
 
-fn parse_payment_info(body: &str) -> (String, String) {
-    // Try to parse JSON payment info
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
-        let amount = json
-            .get("amount")
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown amount");
-        let recipient = json
-            .get("recipient")
-            .or_else(|| json.get("payTo"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown");
-        (amount.to_string(), recipient.to_string())
-    } else {
-        ("unknown amount".to_string(), "unknown".to_string())
+/// How payments are approved before signing: by the approval command if
+/// one is configured, otherwise by prompting when --confirm is set.
+fn approval(config: &Config, json: bool, stdin_taken: bool) -> Option<guard::Confirm> {
+    if let Some(ref command) = config.approve_cmd {
+        let command = command.clone();
+        return Some(Box::new(move |url: &str, required: &PaymentRequired| {
+            approve::approve(&command, url, required, json).map(|()| true)
+        }));
     }
+    if config.confirm {
+        return Some(Box::new(move |_: &str, required: &PaymentRequired| {
+            prompt_confirmation(required, stdin_taken)
+        }));
+    }
+    None
 }
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    match run().await {
+    let args = Args::parse_args();
+    let mut report = JsonReport::default();
+    let result = run(&args, &mut report).await;
+
+    let exit_code = match result {
         Ok(()) => ExitCode::SUCCESS,
-        Err(e) => {
-            eprintln!("Error [{}]: {}", e.code_number(), e);
-            e.exit_code()
-        }
+        Err(ref e) => e.exit_code(),
+    };
+    if args.x402_json {
+        report.print(&result);
+    } else if let Err(e) = result {
+        eprintln!("Error [{}]: {}", e.code_number(), e);
     }
+    exit_code
 }
 
-async fn run() -> Result<(), X402Error> {
-    let args = Args::parse_args();
-    let config = Config::load(
-        args.x402_key.as_deref(),
-        args.x402_wallet.as_deref(),
-        args.x402_wallet_password.as_deref(),
-    )?;
+async fn run(args: &Args, report: &mut JsonReport) -> Result<(), X402Error> {
+    if let Some(ref command) = args.command {
+        return match command {
+            Command::Keyring(command) => secrets::run(command),
+            Command::Wallet(command) => wallet::run(command),
+            Command::Config(ConfigCommand::Show { explain, profile }) => {
+                Config::load_profile(args, profile.as_deref()).map(|config| config.show(*explain))
+            }
+        };
+    }
+
+    let config = Config::load(args)?;
+    let solana_rpc_url = config.solana_rpc_url.clone();
+
+    if args.x402_balance && args.all_networks {
+        let signer = config.require_evm_signer().await?;
+        let table = balance::query_all_balances(signer.address()).await?;
+        let all_failed = table.all_failed();
+        if args.x402_json {
+            report.balances = Some(table);
+        } else {
+            table.print();
+        }
+        if all_failed {
+            return Err(X402Error::Rpc(
+                "Balance query failed on every network".to_string(),
+            ));
+        }
+        return Ok(());
+    }
 
     if args.x402_balance {
-        let signer = config.require_signer()?;
-        let rpc_url = balance::resolve_rpc(args.x402_rpc_url.as_deref());
-        return balance::query_balance(&signer, &rpc_url, args.x402_token.as_deref()).await;
+        let solana = args
+            .x402_network
+            .as_deref()
+            .and_then(chains::solana_by_network);
+        let balance = match solana {
+            Some(chain) => {
+                if args.x402_token.is_some() {
+                    return Err(X402Error::General(
+                        "--x402-token is not supported on Solana networks".to_string(),
+                    ));
+                }
+                let keypair = config.require_solana_keypair()?;
+                let rpc_url = args
+                    .x402_rpc_url
+                    .clone()
+                    .or_else(|| solana_rpc_url.clone())
+                    .unwrap_or_else(|| chain.rpc_url.to_string());
+                balance::query_solana_balance(&keypair.pubkey().to_string(), chain, &rpc_url)
+                    .await?
+            }
+            None => {
+                let signer = config.require_evm_signer().await?;
+                let rpc_url =
+                    balance::resolve_rpc(config.rpc_url.as_deref(), args.x402_network.as_deref())?;
+                balance::query_balance(signer.address(), &rpc_url, args.x402_token.as_deref())
+                    .await?
+            }
+        };
+        if args.x402_json {
+            report.balance = Some(balance);
+        } else {
+            balance.print();
+        }
+        return Ok(());
+    }
+
+    if args.x402_ledger {
+        let filter = LedgerFilter::from_args(
+            args.x402_ledger_since.as_deref(),
+            args.x402_ledger_until.as_deref(),
+            args.x402_ledger_host.as_deref(),
+        )?;
+        return ledger::print_ledger(&filter);
     }
 
-    let req_config = RequestConfig::from_args(&args)?;
+    let req_config = RequestConfig::from_args(args)?;
 
-    let verbose = args.verbose || config.verbose;
+    let verbose = config.verbose;
+
+    let max_amount = config.limits.max_amount.clone();
+    if let Some(ref max) = max_amount {
+        // Validate up front; the cap is applied per asset once decimals are known
+        balance::parse_token_amount(max, 18)?;
+    }
+    budget::validate(&config.budget)?;
 
     if verbose {
         eprintln!("> {} {}", req_config.method, req_config.url);
@@ -91,42 +233,67 @@ async fn run() -> Result<(), X402Error> {
 
     // Dry-run mode: make request without payment handling
     if args.x402_dry_run {
-        return dry_run(&req_config, verbose).await;
-    }
-
-    // Confirmation mode: check if payment required and prompt user
-    if args.confirm || config.confirm {
-        // Pre-flight request to check if payment required
-        let preflight_client = reqwest::Client::new();
-        let preflight_response = preflight_client
-            .request(req_config.method.clone(), &req_config.url)
-            .headers(req_config.headers.clone())
-            .send()
-            .await?;
-
-        if preflight_response.status() == reqwest::StatusCode::PAYMENT_REQUIRED {
-            // Extract payment info and prompt
-            let body = preflight_response.text().await.unwrap_or_default();
-            let (amount, recipient) = parse_payment_info(&body);
-
-            if !prompt_confirmation(&amount, &recipient)? {
-                eprintln!("Payment cancelled.");
-                return Ok(());
-            }
-        }
+        return dry_run(&req_config, verbose, args.x402_json, report).await;
     }
 
-    // Get signer from private key or wallet keystore
-    let signer = config.require_signer()?;
+    // Get signers: EVM from an external signer, private key or wallet keystore,
+    // Solana if configured. The EVM signer is only optional when a Solana
+    // keypair stands in for it.
+    let solana_keypair = config.solana_keypair()?;
+    let signer = if config.has_evm_credentials() || solana_keypair.is_none() {
+        Some(config.require_evm_signer().await?)
+    } else {
+        None
+    };
 
+    let payer = signer.as_ref().map(|s| s.address().to_string());
+    let solana_payer = solana_keypair.as_ref().map(|k| k.pubkey().to_string());
     if verbose {
-        eprintln!("* Signing address: {:?}", signer.address());
+        if let Some(ref payer) = payer {
+            eprintln!("* Signing address: {}", payer);
+        }
+        if let Some(ref solana_payer) = solana_payer {
+            eprintln!("* Solana signing address: {}", solana_payer);
+        }
     }
 
-    let signer = Arc::new(signer);
-    let x402_client = X402Client::new()
-        .register(V1Eip155ExactClient::new(signer.clone()))
-        .register(V2Eip155ExactClient::new(signer));
+    let mut x402_client = X402Client::new();
+    match signer {
+        Some(EvmSigner::Local(signer)) => {
+            let signer = Arc::new(signer);
+            x402_client = x402_client
+                .register(V1Eip155ExactClient::new(signer.clone()))
+                .register(V2Eip155ExactClient::new(signer));
+        }
+        Some(EvmSigner::Remote(signer)) => {
+            let signer = Arc::new(signer);
+            x402_client = x402_client
+                .register(V1Eip155ExactClient::new(signer.clone()))
+                .register(V2Eip155ExactClient::new(signer));
+        }
+        None => {}
+    }
+    // One Solana client per cluster, each building payments against its own
+    // cluster's RPC unless the RPC is overridden
+    if let Some(keypair) = solana_keypair {
+        let keypair = Arc::new(keypair);
+        for chain in chains::SOLANA_CHAINS {
+            let rpc_client = Arc::new(RpcClient::new(
+                solana_rpc_url
+                    .clone()
+                    .unwrap_or_else(|| chain.rpc_url.to_string()),
+            ));
+            x402_client = x402_client
+                .register(Cluster::new(
+                    chain,
+                    V1SolanaExactClient::new(keypair.clone(), rpc_client.clone()),
+                ))
+                .register(Cluster::new(
+                    chain,
+                    V2SolanaExactClient::new(keypair.clone(), rpc_client),
+                ));
+        }
+    }
 
     // Build client with x402 middleware
     let client_builder = reqwest::Client::builder();
@@ -136,19 +303,39 @@ async fn run() -> Result<(), X402Error> {
         client_builder.redirect(reqwest::redirect::Policy::none())
     };
 
-    let client: ClientWithMiddleware = client_builder.build()?.with_payments(x402_client).build();
+    let stdin_taken = req_config.body.as_ref().is_some_and(|b| b.reads_stdin())
+        || args.write_out.as_deref() == Some("@-");
+
+    // The guard is registered after the x402 middleware so it sees each 402
+    // response before the x402 client signs a payment for it
+    let guard = PaymentGuard::new(GuardSettings {
+        max_amount,
+        budget: config.budget.clone(),
+        selection: config.selection.clone(),
+        policy: config.policy.clone(),
+        confirm: approval(&config, args.x402_json, stdin_taken),
+        payer: payer.clone(),
+        rpc_url: config.rpc_url.clone(),
+        solana_payer: solana_payer.clone(),
+        solana_rpc_url,
+        verbose,
+    });
+    let paid = guard.paid();
+    let refused = guard.refused();
+    let mut client_builder = reqwest_middleware::ClientBuilder::new(client_builder.build()?)
+        .with(x402_client)
+        .with(guard);
+    if let Some(body) = req_config.body {
+        // Innermost, so the paid retry gets a fresh copy of a streamed body
+        client_builder = client_builder.with(StreamBody::new(body));
+    }
+    let client: ClientWithMiddleware = client_builder.build();
 
     // Build request
+    let method = req_config.method.to_string();
     let mut request = client.request(req_config.method, &req_config.url);
     request = request.headers(req_config.headers);
 
-    // Form data takes precedence over body
-    if let Some(form) = RequestConfig::parse_form(&args.form)? {
-        request = request.multipart(form);
-    } else if let Some(body) = req_config.body {
-        request = request.body(body);
-    }
-
     // Add basic auth if provided
     if let Some(user_pass) = &args.user {
         let parts: Vec<&str> = user_pass.splitn(2, ':').collect();
@@ -160,16 +347,121 @@ async fn run() -> Result<(), X402Error> {
         request = request.basic_auth(user, pass);
     }
 
-    // Send request
-    let response = request.send().await?;
+    // Send request. A 402 the payment policy denied is still shown, as in
+    // a dry run, before failing; one declined at confirmation is not an error.
+    let started = Instant::now();
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            // -w still reports on the transfer: the refused 402, if there was
+            // one, else an http_code of 000 as curl gives for failed requests
+            let mut transfer = Transfer {
+                method,
+                url_effective: req_config.url.clone(),
+                time_total: started.elapsed(),
+                ..Default::default()
+            };
+            let refused = refused.lock().unwrap().take();
+            let cancelled = refused.as_ref().is_some_and(|r| r.cancelled);
+            if let Some(refused) = refused {
+                report.status = Some(402);
+                if refused.cancelled && !args.x402_json {
+                    eprintln!("Payment cancelled.");
+                } else {
+                    show_payment_required(&refused.headers, &refused.body, args.x402_json, report);
+                }
+                transfer.http_code = 402;
+                transfer.headers = refused.headers;
+            }
+            if let Some(ref format) = args.write_out {
+                write_out(format, &transfer)?;
+            }
+            if cancelled {
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+    };
+
+    let mut transfer = Transfer::new(&method, &response);
+
+    // Record the payment if the server accepted it
+    let payment = paid.lock().unwrap().take();
+    if let Some(option) = payment {
+        let settlement = Settlement::from_headers(response.headers());
+        if verbose {
+            if let Some(ref settlement) = settlement {
+                eprintln!("* {}", settlement.describe());
+            }
+        }
+        let accepted = settlement
+            .as_ref()
+            .map_or(response.status().is_success(), |s| s.success);
+        if accepted {
+            let payer = match chains::solana_by_network(&option.network) {
+                Some(_) => solana_payer,
+                None => payer,
+            };
+            transfer.x402_amount = Some(option.display_amount());
+            let entry = LedgerEntry {
+                timestamp: chrono::Utc::now(),
+                url: req_config.url.clone(),
+                method,
+                scheme: option.scheme,
+                network: option.network,
+                asset: option.asset,
+                amount: option.amount.to_string(),
+                pay_to: option.pay_to,
+                payer: payer.unwrap_or_default(),
+                status: response.status().as_u16(),
+                transaction: settlement.as_ref().and_then(|s| s.transaction.clone()),
+            };
+            if let Err(e) = ledger::append(&entry) {
+                eprintln!("Warning: payment not recorded in ledger: {}", e);
+            }
+            if let Some(ref path) = args.x402_receipt {
+                if let Err(e) = write_receipt(path, &entry, settlement.as_ref()) {
+                    eprintln!("Warning: receipt not written: {}", e);
+                }
+            }
+            transfer.x402_network = Some(entry.network.clone());
+            transfer.x402_tx_hash = entry.transaction.clone();
+            report.payment = Some(entry);
+        }
+        report.settlement = settlement;
+    }
+    report.status = Some(response.status().as_u16());
 
     // Handle response
-    handle_response(response, args.output.as_deref(), args.fail, verbose).await?;
+    let settings = ResponseSettings {
+        output_file: args.output.as_deref(),
+        fail_on_error: args.fail,
+        verbose,
+        no_buffer: args.no_buffer,
+        sse_json: args.x402_sse_json,
+        include_headers: args.include,
+        head_only: args.head,
+        dump_header: args.dump_header.as_deref(),
+    };
+    let result = handle_response(response, &settings).await;
+
+    // Like curl, report on the transfer even when -f failed it
+    if let Some(ref format) = args.write_out {
+        transfer.size_download = *result.as_ref().unwrap_or(&0);
+        transfer.time_total = started.elapsed();
+        write_out(format, &transfer)?;
+    }
+    result?;
 
     Ok(())
 }
 
-async fn dry_run(req_config: &RequestConfig, verbose: bool) -> Result<(), X402Error> {
+async fn dry_run(
+    req_config: &RequestConfig,
+    verbose: bool,
+    json: bool,
+    report: &mut JsonReport,
+) -> Result<(), X402Error> {
     let client = reqwest::Client::new();
     let response = client
         .request(req_config.method.clone(), &req_config.url)
@@ -177,34 +469,70 @@ async fn dry_run(req_config: &RequestConfig, verbose: bool) -> Result<(), X402Er
         .send()
         .await?;
 
+    report.status = Some(response.status().as_u16());
+
     if response.status() == reqwest::StatusCode::PAYMENT_REQUIRED {
+        let headers = response.headers().clone();
+        let body = response.bytes().await.unwrap_or_default();
+
+        show_payment_required(&headers, &body, json, report);
+        if json {
+            return Ok(());
+        }
+        eprintln!("(dry run - no payment made)");
+    } else if verbose {
+        eprintln!("< {} (no payment required)", response.status());
+    }
+
+    Ok(())
+}
+
+/// Show the payment requirements of a 402 response: in the JSON report, or
+/// on stderr.
+fn show_payment_required(headers: &HeaderMap, body: &[u8], json: bool, report: &mut JsonReport) {
+    if json {
+        report.payment_required = Some(match PaymentRequired::parse(headers, body) {
+            Some(required) => required.to_json(),
+            None => serde_json::json!({ "body": String::from_utf8_lossy(body) }),
+        });
+        return;
+    }
+
+    if let Some(required) = PaymentRequired::parse(headers, body) {
+        eprintln!("Payment required (x402 v{}):", required.x402_version);
+        if let Some(ref error) = required.error {
+            eprintln!("  Reason: {}", error);
+        }
+        print_payment_options(&required);
+        for option in &required.options {
+            if option.decimals().is_none() {
+                eprintln!(
+                    "  Note: {} is not a known token; amount shown in atomic units",
+                    option.asset
+                );
+            }
+        }
+    } else {
         eprintln!("Payment required:");
 
-        // Try to extract x402 headers
-        for (name, value) in response.headers() {
+        // Not an x402 response: show whatever the server sent
+        for (name, value) in headers.iter() {
             let name_str = name.as_str().to_lowercase();
             if name_str.starts_with("x-402") || name_str.starts_with("x402") {
                 eprintln!("  {}: {}", name, value.to_str().unwrap_or("<binary>"));
             }
         }
 
-        // Try to parse body for payment details
-        let body = response.text().await.unwrap_or_default();
+        let body = String::from_utf8_lossy(body);
         if !body.is_empty() {
             if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
                 eprintln!(
                     "  Payment details: {}",
-                    serde_json::to_string_pretty(&json).unwrap_or(body)
+                    serde_json::to_string_pretty(&json).unwrap_or_else(|_| body.to_string())
                 );
             } else {
                 eprintln!("  Body: {}", body);
             }
         }
-
-        eprintln!("(dry run - no payment made)");
-    } else if verbose {
-        eprintln!("< {} (no payment required)", response.status());
     }
-
-    Ok(())
 }
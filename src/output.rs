@@ -1,17 +1,237 @@
+use crate::balance::{BalanceReport, BalanceTable};
 use crate::error::X402Error;
-use reqwest::Response;
+use crate::ledger::LedgerEntry;
+use crate::payment::Settlement;
+use crate::sse;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Response, Version};
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, IsTerminal, Write};
 
+/// Structured result printed to stderr by `--x402-json`, in place of the
+/// human-readable diagnostics. The response body still goes to stdout.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct JsonReport {
+    pub success: bool,
+    /// HTTP status of the final response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Payment requirements from a 402 response (dry run or cancelled payment)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_required: Option<serde_json::Value>,
+    /// Receipt for a payment the server accepted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment: Option<LedgerEntry>,
+    /// Settlement result decoded from the payment-response header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement: Option<Settlement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<BalanceReport>,
+    /// Per-network balances from `--x402-balance --all-networks`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balances: Option<BalanceTable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ErrorReport {
+    /// Same as the process exit code
+    pub code: u8,
+    pub message: String,
+}
+
+impl JsonReport {
+    /// Print the report as a single line of JSON on stderr.
+    pub fn print(mut self, result: &Result<(), X402Error>) {
+        self.success = result.is_ok();
+        if let Err(e) = result {
+            self.error = Some(ErrorReport {
+                code: e.code_number(),
+                message: e.to_string(),
+            });
+        }
+        match serde_json::to_string(&self) {
+            Ok(json) => eprintln!("{}", json),
+            Err(e) => eprintln!("Failed to encode JSON report: {}", e),
+        }
+    }
+}
+
+/// Proof of payment written by `--x402-receipt`.
+#[derive(Debug, serde::Serialize)]
+struct Receipt<'a> {
+    payment: &'a LedgerEntry,
+    settlement: Option<&'a Settlement>,
+}
+
+/// Write the receipt for a payment as pretty-printed JSON.
+pub fn write_receipt(
+    path: &str,
+    payment: &LedgerEntry,
+    settlement: Option<&Settlement>,
+) -> Result<(), X402Error> {
+    let receipt = Receipt {
+        payment,
+        settlement,
+    };
+    let json = serde_json::to_string_pretty(&receipt)
+        .map_err(|e| X402Error::General(format!("Failed to encode receipt: {}", e)))?;
+    std::fs::write(path, json + "\n")
+        .map_err(|e| X402Error::General(format!("Failed to write receipt: {}", e)))
+}
+
+/// How to write out a response.
+#[derive(Debug, Default)]
+pub struct ResponseSettings<'a> {
+    /// Write the body to this file instead of stdout
+    pub output_file: Option<&'a str>,
+    /// Fail on HTTP error statuses
+    pub fail_on_error: bool,
+    pub verbose: bool,
+    /// Flush each chunk as it arrives instead of buffering output
+    pub no_buffer: bool,
+    /// Print the data of each server-sent event as a line of JSON
+    pub sse_json: bool,
+    /// Write the status line and headers before the body
+    pub include_headers: bool,
+    /// Write the status line and headers only
+    pub head_only: bool,
+    /// Also write the status line and headers to this file (`-` for stdout)
+    pub dump_header: Option<&'a str>,
+}
+
+/// Why streaming a body failed: reading from the server or writing it out.
+enum StreamError {
+    Read(reqwest::Error),
+    Write(std::io::Error),
+}
+
+fn write_error(e: StreamError, context: &str) -> X402Error {
+    match e {
+        StreamError::Read(e) => X402Error::Network(e),
+        StreamError::Write(e) => X402Error::General(format!("{}: {}", context, e)),
+    }
+}
+
+/// The status line and headers as curl prints them: CRLF line endings and
+/// a blank line at the end. reqwest doesn't keep the case of header names,
+/// so HTTP/1.x ones are capitalized the way servers usually send them;
+/// HTTP/2 and later, whose names are lowercase on the wire, have no reason
+/// phrase.
+fn format_head(response: &Response) -> Vec<u8> {
+    let status = response.status();
+    let mut head = match response.version() {
+        Version::HTTP_2 => format!("HTTP/2 {} \r\n", status.as_u16()),
+        Version::HTTP_3 => format!("HTTP/3 {} \r\n", status.as_u16()),
+        version => format!(
+            "{} {} {}\r\n",
+            if version == Version::HTTP_10 {
+                "HTTP/1.0"
+            } else {
+                "HTTP/1.1"
+            },
+            status.as_u16(),
+            status.canonical_reason().unwrap_or("")
+        ),
+    }
+    .into_bytes();
+    let title_case = response.version() < Version::HTTP_2;
+    for (name, value) in response.headers() {
+        if title_case {
+            head.extend(capitalize(name.as_str()).as_bytes());
+        } else {
+            head.extend(name.as_str().as_bytes());
+        }
+        head.extend(b": ");
+        head.extend(value.as_bytes());
+        head.extend(b"\r\n");
+    }
+    head.extend(b"\r\n");
+    head
+}
+
+/// `content-type` -> `Content-Type`
+fn capitalize(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/event-stream"))
+}
+
+/// What [`stream_body`] wrote.
+struct Streamed {
+    /// Last byte written
+    last: Option<u8>,
+    /// Bytes of body received
+    body_len: u64,
+}
+
+/// Copy `head` and then the response body to `out`, the body chunk by
+/// chunk as it arrives. Event streams are converted to JSON lines with
+/// `sse_json`, and flushed event by event.
+async fn stream_body(
+    mut response: Response,
+    out: impl Write,
+    head: &[u8],
+    settings: &ResponseSettings<'_>,
+) -> Result<Streamed, StreamError> {
+    let mut out = BufWriter::new(out);
+    out.write_all(head).map_err(StreamError::Write)?;
+    let mut last = head.last().copied();
+    let mut body_len = 0;
+    if settings.head_only {
+        out.flush().map_err(StreamError::Write)?;
+        return Ok(Streamed { last, body_len });
+    }
+    let mut events = (settings.sse_json && is_event_stream(&response)).then(sse::Parser::default);
+    while let Some(chunk) = response.chunk().await.map_err(StreamError::Read)? {
+        body_len += chunk.len() as u64;
+        match events.as_mut() {
+            Some(parser) => {
+                for data in parser.feed(&chunk) {
+                    writeln!(out, "{}", sse::to_json_line(&data)).map_err(StreamError::Write)?;
+                    out.flush().map_err(StreamError::Write)?;
+                    last = Some(b'\n');
+                }
+            }
+            None => {
+                out.write_all(&chunk).map_err(StreamError::Write)?;
+                if settings.no_buffer {
+                    out.flush().map_err(StreamError::Write)?;
+                }
+                last = chunk.last().copied().or(last);
+            }
+        }
+    }
+    out.flush().map_err(StreamError::Write)?;
+    Ok(Streamed { last, body_len })
+}
+
+/// Write out a response as `settings` ask. Returns the size of the body
+/// received, in bytes.
 pub async fn handle_response(
     response: Response,
-    output_file: Option<&str>,
-    fail_on_error: bool,
-    verbose: bool,
-) -> Result<(), X402Error> {
+    settings: &ResponseSettings<'_>,
+) -> Result<u64, X402Error> {
     let status = response.status();
 
-    if verbose {
+    if settings.verbose {
         eprintln!(
             "< {} {}",
             status.as_u16(),
@@ -19,7 +239,7 @@ pub async fn handle_response(
         );
     }
 
-    if fail_on_error && !status.is_success() {
+    if settings.fail_on_error && !status.is_success() {
         return Err(X402Error::Http(format!(
             "HTTP {} {}",
             status.as_u16(),
@@ -27,26 +247,74 @@ pub async fn handle_response(
         )));
     }
 
-    let body = response.bytes().await?;
+    let head = format_head(&response);
+    match settings.dump_header {
+        Some("-") => std::io::stdout()
+            .write_all(&head)
+            .map_err(|e| X402Error::General(format!("Failed to write to stdout: {}", e)))?,
+        Some(path) => std::fs::write(path, &head)
+            .map_err(|e| X402Error::General(format!("Failed to write header file: {}", e)))?,
+        None => {}
+    }
+    let head = if settings.include_headers || settings.head_only {
+        &head[..]
+    } else {
+        &[]
+    };
 
-    match output_file {
+    let streamed = match settings.output_file {
         Some(path) => {
-            let mut file = File::create(path)
+            let file = File::create(path)
                 .map_err(|e| X402Error::General(format!("Failed to create output file: {}", e)))?;
-            file.write_all(&body)
-                .map_err(|e| X402Error::General(format!("Failed to write output file: {}", e)))?;
+            stream_body(response, file, head, settings)
+                .await
+                .map_err(|e| write_error(e, "Failed to write output file"))?
         }
         None => {
-            // Write to stdout
-            std::io::stdout()
-                .write_all(&body)
-                .map_err(|e| X402Error::General(format!("Failed to write to stdout: {}", e)))?;
-            // Add newline if output doesn't end with one
-            if !body.ends_with(b"\n") {
+            let stdout = std::io::stdout();
+            let streamed = stream_body(response, stdout.lock(), head, settings)
+                .await
+                .map_err(|e| write_error(e, "Failed to write to stdout"))?;
+            // On a terminal, end with a newline so the prompt starts on its own
+            // line; piped or redirected output is left byte-for-byte intact
+            if stdout.is_terminal() && streamed.last != Some(b'\n') {
                 println!();
             }
+            streamed
         }
+    };
+
+    Ok(streamed.body_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(version: Version) -> Response {
+        let response = http::Response::builder()
+            .status(402)
+            .version(version)
+            .header("content-type", "application/json")
+            .header("x-payment-response", "e30=")
+            .body("")
+            .unwrap();
+        Response::from(response)
+    }
+
+    #[test]
+    fn test_format_head_http1() {
+        assert_eq!(
+            String::from_utf8(format_head(&response(Version::HTTP_11))).unwrap(),
+            "HTTP/1.1 402 Payment Required\r\nContent-Type: application/json\r\nX-Payment-Response: e30=\r\n\r\n"
+        );
     }
 
-    Ok(())
+    #[test]
+    fn test_format_head_http2() {
+        assert_eq!(
+            String::from_utf8(format_head(&response(Version::HTTP_2))).unwrap(),
+            "HTTP/2 402 \r\ncontent-type: application/json\r\nx-payment-response: e30=\r\n\r\n"
+        );
+    }
 }
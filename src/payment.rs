@@ -0,0 +1,716 @@
+use crate::balance::format_token_balance;
+use crate::chains;
+use crate::error::X402Error;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_LENGTH};
+use x402_types::proto::{v1, v2};
+
+/// Header carrying base64-encoded payment requirements in x402 v2.
+pub const PAYMENT_REQUIRED_HEADER: &str = "payment-required";
+/// Request header carrying the signed payment (v1).
+pub const X_PAYMENT_HEADER: &str = "x-payment";
+/// Request header carrying the signed payment (v2).
+pub const PAYMENT_SIGNATURE_HEADER: &str = "payment-signature";
+/// Response header carrying the settlement result (v1).
+pub const X_PAYMENT_RESPONSE_HEADER: &str = "x-payment-response";
+/// Response header carrying the settlement result (v2).
+pub const PAYMENT_RESPONSE_HEADER: &str = "payment-response";
+
+/// Where the payment requirements were found in the 402 response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// v1: JSON response body
+    Body,
+    /// v2: `PAYMENT-REQUIRED` header
+    Header,
+}
+
+/// One entry of the `accepts` list in a 402 response.
+#[derive(Debug, Clone)]
+pub struct PaymentOption {
+    pub scheme: String,
+    pub network: String,
+    pub asset: String,
+    /// Amount in the asset's atomic units
+    pub amount: u128,
+    pub pay_to: String,
+    raw: serde_json::Value,
+}
+
+/// The fields x402curl reads from an `accepts` entry, as the SDK's v1/v2
+/// types decoded it.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawOption {
+    scheme: String,
+    network: String,
+    asset: String,
+    /// v1
+    #[serde(default)]
+    max_amount_required: Option<String>,
+    /// v2
+    #[serde(default)]
+    amount: Option<String>,
+    pay_to: String,
+}
+
+/// Settlement result returned by the server after a paid request.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Settlement {
+    pub success: bool,
+    /// Settlement transaction hash
+    #[serde(default)]
+    pub transaction: Option<String>,
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub payer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
+    /// The header as sent by the server (base64 JSON), kept as proof of payment
+    #[serde(skip_deserializing)]
+    pub header: String,
+}
+
+/// Payment requirements decoded from a 402 response.
+#[derive(Debug, Clone)]
+pub struct PaymentRequired {
+    pub x402_version: u64,
+    /// Reason given by the server, e.g. "X-PAYMENT header is required"
+    pub error: Option<String>,
+    pub options: Vec<PaymentOption>,
+    document: serde_json::Value,
+    source: Source,
+    changed: bool,
+}
+
+impl PaymentOption {
+    /// Read an `accepts` entry of an x402 `version` document from its
+    /// `decoded` form, keeping the entry as received in `raw`.
+    fn from_value(
+        decoded: serde_json::Value,
+        version: u64,
+        raw: serde_json::Value,
+    ) -> Result<Self, String> {
+        let parsed: RawOption = serde_json::from_value(decoded).map_err(|e| e.to_string())?;
+        let amount = match version {
+            1 => parsed.max_amount_required,
+            _ => parsed.amount,
+        }
+        .ok_or("missing amount")?;
+        Ok(PaymentOption {
+            scheme: parsed.scheme,
+            network: parsed.network,
+            asset: parsed.asset,
+            amount: amount
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid amount {}", amount))?,
+            pay_to: parsed.pay_to,
+            raw,
+        })
+    }
+
+    /// Find the option a signed payment header was created for.
+    /// v2 payloads carry the accepted requirements; v1 payloads only name
+    /// the scheme and network, so they are matched against `offered`.
+    pub fn from_payment_header(value: &HeaderValue, offered: &[PaymentOption]) -> Option<Self> {
+        let payload = decode_header(value)?;
+        if let Some(accepted) = payload.get("accepted") {
+            return Self::from_value(accepted.clone(), 2, accepted.clone()).ok();
+        }
+
+        let scheme = payload.get("scheme")?.as_str()?;
+        let network = payload.get("network")?.as_str()?;
+        offered
+            .iter()
+            .find(|o| o.scheme == scheme && o.network == network)
+            .cloned()
+    }
+
+    /// Decimals of the option's asset, if it is a token we know.
+    pub fn decimals(&self) -> Option<u8> {
+        token_decimals(&self.network, &self.asset)
+    }
+
+    /// Token symbol, or the asset address if the token is unknown.
+    pub fn symbol(&self) -> &str {
+        match self.decimals() {
+            Some(_) => "USDC",
+            None => &self.asset,
+        }
+    }
+
+    /// Amount in token units, e.g. "0.010000".
+    /// Amounts of unknown assets are shown in atomic units.
+    pub fn display_amount(&self) -> String {
+        match self.decimals() {
+            Some(decimals) => format_token_balance(self.amount, decimals),
+            None => self.amount.to_string(),
+        }
+    }
+
+    /// JSON representation used by `--x402-json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "scheme": self.scheme,
+            "network": self.network,
+            "network_name": network_name(&self.network),
+            "asset": self.asset,
+            "symbol": self.symbol(),
+            "amount": self.amount.to_string(),
+            "amount_formatted": self.display_amount(),
+            "pay_to": self.pay_to,
+        })
+    }
+
+    /// Human-readable amount, e.g. "0.010000 USDC on base-sepolia".
+    pub fn describe(&self) -> String {
+        format!(
+            "{} on {}",
+            format_amount(self.amount, &self.network, &self.asset),
+            self.network
+        )
+    }
+}
+
+impl Settlement {
+    /// Decode the settlement header of a paid response, if present.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let value = headers
+            .get(PAYMENT_RESPONSE_HEADER)
+            .or_else(|| headers.get(X_PAYMENT_RESPONSE_HEADER))?;
+        let mut settlement: Settlement = serde_json::from_value(decode_header(value)?).ok()?;
+        settlement.header = value.to_str().ok()?.to_string();
+        Some(settlement)
+    }
+
+    /// One-line summary for verbose output.
+    pub fn describe(&self) -> String {
+        if !self.success {
+            return format!(
+                "Payment settlement failed: {}",
+                self.error_reason.as_deref().unwrap_or("unknown reason")
+            );
+        }
+        let mut summary = format!(
+            "Payment settled: {}",
+            self.transaction
+                .as_deref()
+                .unwrap_or("(no transaction hash)")
+        );
+        if let Some(ref network) = self.network {
+            summary.push_str(&format!(" on {}", network));
+        }
+        if let Some(ref payer) = self.payer {
+            summary.push_str(&format!(" (payer {})", payer));
+        }
+        summary
+    }
+}
+
+fn decode_header(value: &HeaderValue) -> Option<serde_json::Value> {
+    let decoded = BASE64.decode(value.as_bytes()).ok()?;
+    serde_json::from_slice(&decoded).ok()
+}
+
+/// A document as received, and as re-encoded from the SDK's types.
+type Decoded = (serde_json::Value, serde_json::Value, Source);
+
+/// Decode payment requirements with the SDK's types, as the x402 client
+/// does: v2 from the `PAYMENT-REQUIRED` header, or else v1 from the body.
+/// Returns `Ok(None)` for a response that isn't x402 at all.
+fn decode_sdk(headers: &HeaderMap, body: &[u8]) -> Result<Option<Decoded>, String> {
+    fn decode<T: serde::de::DeserializeOwned + serde::Serialize>(
+        json: &[u8],
+    ) -> Result<(serde_json::Value, serde_json::Value), String> {
+        let typed: T = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+        let decoded = serde_json::to_value(typed).map_err(|e| e.to_string())?;
+        let raw = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+        Ok((raw, decoded))
+    }
+
+    let mut header_error = None;
+    if let Some(value) = headers.get(PAYMENT_REQUIRED_HEADER) {
+        let result = BASE64
+            .decode(value.as_bytes())
+            .map_err(|e| e.to_string())
+            .and_then(|json| decode::<v2::PaymentRequired>(&json));
+        match result {
+            Ok((raw, decoded)) => return Ok(Some((raw, decoded, Source::Header))),
+            Err(e) => header_error = Some(e),
+        }
+    }
+
+    let is_v1 = serde_json::from_slice::<serde_json::Value>(body)
+        .is_ok_and(|document| document.get("x402Version").is_some());
+    if is_v1 {
+        let (raw, decoded) = decode::<v1::PaymentRequired>(body)?;
+        return Ok(Some((raw, decoded, Source::Body)));
+    }
+    header_error.map_or(Ok(None), Err)
+}
+
+/// Display name for a network, e.g. "Base Sepolia (base-sepolia)".
+pub fn network_name(network: &str) -> String {
+    let name = chains::by_network(network)
+        .map(|c| c.name)
+        .or_else(|| chains::solana_by_network(network).map(|c| c.name));
+    match name {
+        Some(name) => format!("{} ({})", name, network),
+        None => network.to_string(),
+    }
+}
+
+/// Decimals of an asset on a network, if it is a token we know.
+pub fn token_decimals(network: &str, asset: &str) -> Option<u8> {
+    if let Some(chain) = chains::solana_by_network(network) {
+        return chain.is_usdc(asset).then_some(chain.usdc_decimals);
+    }
+    let chain = chains::by_network(network)?;
+    chain.is_usdc(asset).then_some(chain.usdc_decimals)
+}
+
+/// Format an atomic amount in token units, e.g. "0.010000 USDC".
+/// Amounts of unknown assets are shown in atomic units.
+pub fn format_amount(amount: u128, network: &str, asset: &str) -> String {
+    match token_decimals(network, asset) {
+        Some(decimals) => format!("{} USDC", format_token_balance(amount, decimals)),
+        None => format!("{} units of {}", amount, asset),
+    }
+}
+
+impl PaymentRequired {
+    /// Decode payment requirements from a 402 response.
+    /// Returns `None` if the response doesn't carry x402 requirements, or
+    /// they can't be decoded.
+    pub fn parse(headers: &HeaderMap, body: &[u8]) -> Option<Self> {
+        Self::decode(headers, body).ok().flatten()
+    }
+
+    /// Decode payment requirements from a 402 response, reading exactly
+    /// what the x402 client will: every option the SDK's types decode, with
+    /// the values they decode to. Returns `Ok(None)` if the response doesn't
+    /// carry x402 requirements, and fails with [`X402Error::Payment`] if it
+    /// does but they can't all be read.
+    pub fn decode(headers: &HeaderMap, body: &[u8]) -> Result<Option<Self>, X402Error> {
+        let undecodable = |e: String| {
+            X402Error::Payment(format!("cannot decode the payment requirements: {}", e))
+        };
+        let Some((document, decoded, source)) = decode_sdk(headers, body).map_err(undecodable)?
+        else {
+            return Ok(None);
+        };
+
+        let x402_version = decoded["x402Version"]
+            .as_u64()
+            .ok_or_else(|| undecodable("missing x402Version".to_string()))?;
+        let error = decoded["error"].as_str().map(|e| e.to_string());
+        let (Some(accepts), Some(raw_accepts)) = (
+            decoded["accepts"].as_array(),
+            document["accepts"].as_array(),
+        ) else {
+            return Err(undecodable("missing accepts".to_string()));
+        };
+        if accepts.len() != raw_accepts.len() {
+            return Err(undecodable("accepts changed while decoding".to_string()));
+        }
+        let options = accepts
+            .iter()
+            .zip(raw_accepts)
+            .enumerate()
+            .map(|(i, (decoded, raw))| {
+                PaymentOption::from_value(decoded.clone(), x402_version, raw.clone())
+                    .map_err(|e| undecodable(format!("option {}: {}", i + 1, e)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(PaymentRequired {
+            x402_version,
+            error,
+            options,
+            document,
+            source,
+            changed: false,
+        }))
+    }
+
+    /// JSON representation used by `--x402-json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "x402_version": self.x402_version,
+            "error": self.error,
+            "accepts": self.options.iter().map(PaymentOption::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Keep only the options matching `keep`.
+    pub fn retain(&mut self, keep: impl FnMut(&PaymentOption) -> bool) {
+        let before = self.options.len();
+        self.options.retain(keep);
+        self.changed |= self.options.len() != before;
+    }
+
+    /// Stable-sort the options by `key`.
+    pub fn sort_by_key<K: Ord>(&mut self, mut key: impl FnMut(&PaymentOption) -> K) {
+        let mut keyed: Vec<_> = self
+            .options
+            .drain(..)
+            .enumerate()
+            .map(|(i, option)| (key(&option), i, option))
+            .collect();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+        self.changed |= keyed.iter().enumerate().any(|(pos, (_, i, _))| pos != *i);
+        self.options = keyed.into_iter().map(|(_, _, option)| option).collect();
+    }
+
+    /// Re-encode the (possibly filtered or reordered) requirements into the response
+    /// headers and body they were decoded from.
+    pub fn encode(&self, mut headers: HeaderMap, body: Vec<u8>) -> (HeaderMap, Vec<u8>) {
+        if !self.changed {
+            return (headers, body);
+        }
+
+        let mut document = self.document.clone();
+        document["accepts"] =
+            serde_json::Value::Array(self.options.iter().map(|o| o.raw.clone()).collect());
+        let json = serde_json::to_vec(&document).unwrap_or_default();
+
+        match self.source {
+            Source::Body => {
+                headers.remove(CONTENT_LENGTH);
+                (headers, json)
+            }
+            Source::Header => {
+                if let Ok(value) = HeaderValue::from_str(&BASE64.encode(json)) {
+                    headers.insert(PAYMENT_REQUIRED_HEADER, value);
+                }
+                (headers, body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fixtures::{v1_body, v2_document, USDC};
+
+    fn v2_headers() -> HeaderMap {
+        let document = v2_document(&[
+            (
+                "eip155:8453",
+                "2500000",
+                "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            ),
+            ("eip155:84532", "1000", USDC),
+        ]);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            PAYMENT_REQUIRED_HEADER,
+            HeaderValue::from_str(&BASE64.encode(document)).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_parse_v1_body() {
+        let required =
+            PaymentRequired::parse(&HeaderMap::new(), v1_body("10000", USDC).as_bytes()).unwrap();
+        assert_eq!(required.x402_version, 1);
+        assert_eq!(
+            required.error.as_deref(),
+            Some("X-PAYMENT header is required")
+        );
+        assert_eq!(required.options.len(), 1);
+
+        let option = &required.options[0];
+        assert_eq!(option.amount, 10_000);
+        assert_eq!(chains::by_network(&option.network).unwrap().id, 84532);
+        assert_eq!(option.decimals(), Some(6));
+        assert_eq!(option.describe(), "0.010000 USDC on base-sepolia");
+        assert_eq!(option.symbol(), "USDC");
+        assert_eq!(option.display_amount(), "0.010000");
+    }
+
+    #[test]
+    fn test_parse_v2_header() {
+        let required = PaymentRequired::parse(&v2_headers(), b"").unwrap();
+        assert_eq!(required.x402_version, 2);
+        assert_eq!(required.options.len(), 2);
+        assert_eq!(required.options[0].amount, 2_500_000);
+        assert_eq!(
+            chains::by_network(&required.options[0].network).unwrap().id,
+            8453
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let required =
+            PaymentRequired::parse(&HeaderMap::new(), v1_body("10000", USDC).as_bytes()).unwrap();
+        let json = required.to_json();
+        assert_eq!(json["x402_version"], 1);
+        assert_eq!(json["accepts"][0]["amount"], "10000");
+        assert_eq!(json["accepts"][0]["amount_formatted"], "0.010000");
+        assert_eq!(json["accepts"][0]["symbol"], "USDC");
+        assert_eq!(
+            json["accepts"][0]["network_name"],
+            "Base Sepolia (base-sepolia)"
+        );
+    }
+
+    #[test]
+    fn test_parse_not_x402() {
+        assert!(PaymentRequired::parse(&HeaderMap::new(), b"Payment Required").is_none());
+        assert!(matches!(
+            PaymentRequired::decode(&HeaderMap::new(), b"{\"error\":\"pay\"}"),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn test_decode_reads_what_the_client_pays() {
+        // v1 pays maxAmountRequired, whatever else the entry says
+        let mut body: serde_json::Value =
+            serde_json::from_str(&v1_body("100000000", USDC)).unwrap();
+        body["accepts"][0]["amount"] = "1".into();
+        let required =
+            PaymentRequired::parse(&HeaderMap::new(), body.to_string().as_bytes()).unwrap();
+        assert_eq!(required.options[0].amount, 100_000_000);
+    }
+
+    #[test]
+    fn test_decode_fails_on_unreadable_option() {
+        let mut body: serde_json::Value = serde_json::from_str(&v1_body("10000", USDC)).unwrap();
+        body["accepts"][0].as_object_mut().unwrap().remove("payTo");
+        let err =
+            PaymentRequired::decode(&HeaderMap::new(), body.to_string().as_bytes()).unwrap_err();
+        assert!(matches!(err, X402Error::Payment(_)));
+        assert!(PaymentRequired::parse(&HeaderMap::new(), body.to_string().as_bytes()).is_none());
+    }
+
+    #[test]
+    fn test_unknown_asset_has_no_decimals() {
+        let body = v1_body("10000", "0x0000000000000000000000000000000000000001");
+        let required = PaymentRequired::parse(&HeaderMap::new(), body.as_bytes()).unwrap();
+        assert_eq!(required.options[0].decimals(), None);
+        assert_eq!(
+            required.options[0].symbol(),
+            "0x0000000000000000000000000000000000000001"
+        );
+        assert_eq!(required.options[0].display_amount(), "10000");
+    }
+
+    #[test]
+    fn test_solana_usdc_decimals() {
+        assert_eq!(
+            token_decimals(
+                "solana-devnet",
+                "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"
+            ),
+            Some(6)
+        );
+        assert_eq!(
+            format_amount(
+                10_000,
+                "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp",
+                "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+            ),
+            "0.010000 USDC"
+        );
+        // Mint addresses are case-sensitive
+        assert_eq!(
+            token_decimals("solana", "epjfwdd5aufqssqem2qn1xzybapc8g4wegGkzwytdt1v"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_network_name() {
+        assert_eq!(network_name("base-sepolia"), "Base Sepolia (base-sepolia)");
+        assert_eq!(network_name("eip155:8453"), "Base (eip155:8453)");
+        assert_eq!(network_name("eip155:99999"), "eip155:99999");
+        assert_eq!(
+            network_name("solana-devnet"),
+            "Solana Devnet (solana-devnet)"
+        );
+        assert_eq!(network_name("solana-testnet"), "solana-testnet");
+    }
+
+    #[test]
+    fn test_paid_option_v1_matches_offered() {
+        let required =
+            PaymentRequired::parse(&HeaderMap::new(), v1_body("10000", USDC).as_bytes()).unwrap();
+        let payload = r#"{"x402Version":1,"scheme":"exact","network":"base-sepolia","payload":{}}"#;
+        let header = HeaderValue::from_str(&BASE64.encode(payload)).unwrap();
+
+        let option = PaymentOption::from_payment_header(&header, &required.options).unwrap();
+        assert_eq!(option.amount, 10_000);
+        assert_eq!(option.pay_to, "0x209693Bc6afc0C5328bA36FaF03C514EF312287C");
+    }
+
+    #[test]
+    fn test_paid_option_v2_accepted() {
+        let payload = serde_json::json!({
+            "x402Version": 2,
+            "accepted": {
+                "scheme": "exact",
+                "network": "eip155:84532",
+                "amount": "1000",
+                "payTo": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+                "asset": "0x036CbD53842c5426634e7929541eC2318f3dCF7e"
+            },
+            "payload": {}
+        });
+        let header = HeaderValue::from_str(&BASE64.encode(payload.to_string())).unwrap();
+
+        let option = PaymentOption::from_payment_header(&header, &[]).unwrap();
+        assert_eq!(option.describe(), "0.001000 USDC on eip155:84532");
+    }
+
+    #[test]
+    fn test_settlement_from_headers() {
+        let settlement = r#"{"success":true,"transaction":"0xabc","network":"base-sepolia"}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            X_PAYMENT_RESPONSE_HEADER,
+            HeaderValue::from_str(&BASE64.encode(settlement)).unwrap(),
+        );
+
+        let settlement = Settlement::from_headers(&headers).unwrap();
+        assert!(settlement.success);
+        assert_eq!(settlement.transaction.as_deref(), Some("0xabc"));
+        assert_eq!(
+            settlement.header,
+            BASE64.encode(r#"{"success":true,"transaction":"0xabc","network":"base-sepolia"}"#)
+        );
+        assert_eq!(
+            settlement.describe(),
+            "Payment settled: 0xabc on base-sepolia"
+        );
+    }
+
+    #[test]
+    fn test_settlement_failure() {
+        let settlement = r#"{"success":false,"errorReason":"insufficient_funds","transaction":""}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            PAYMENT_RESPONSE_HEADER,
+            HeaderValue::from_str(&BASE64.encode(settlement)).unwrap(),
+        );
+
+        let settlement = Settlement::from_headers(&headers).unwrap();
+        assert!(!settlement.success);
+        assert_eq!(
+            settlement.describe(),
+            "Payment settlement failed: insufficient_funds"
+        );
+    }
+
+    #[test]
+    fn test_encode_unchanged_keeps_body() {
+        let required =
+            PaymentRequired::parse(&HeaderMap::new(), v1_body("10000", USDC).as_bytes()).unwrap();
+        let (_, body) =
+            required.encode(HeaderMap::new(), v1_body("10000", USDC).as_bytes().to_vec());
+        assert_eq!(body, v1_body("10000", USDC).as_bytes());
+    }
+
+    #[test]
+    fn test_encode_filtered_v2_header() {
+        let mut required = PaymentRequired::parse(&v2_headers(), b"").unwrap();
+        required.retain(|o| o.amount < 1_000_000);
+
+        let (headers, _) = required.encode(v2_headers(), Vec::new());
+        let reparsed = PaymentRequired::parse(&headers, b"").unwrap();
+        assert_eq!(reparsed.options.len(), 1);
+        assert_eq!(reparsed.options[0].network, "eip155:84532");
+    }
+
+    #[test]
+    fn test_encode_reordered_v2_header() {
+        let mut required = PaymentRequired::parse(&v2_headers(), b"").unwrap();
+        required.sort_by_key(|o| o.amount);
+
+        let (headers, _) = required.encode(v2_headers(), Vec::new());
+        let reparsed = PaymentRequired::parse(&headers, b"").unwrap();
+        assert_eq!(reparsed.options[0].network, "eip155:84532");
+        assert_eq!(reparsed.options[1].network, "eip155:8453");
+    }
+}
+
+/// 402 responses for the tests of the modules that check payments.
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    pub const PAY_TO: &str = "0x209693Bc6afc0C5328bA36FaF03C514EF312287C";
+    /// USDC on Base Sepolia
+    pub const USDC: &str = "0x036CbD53842c5426634e7929541eC2318f3dCF7e";
+
+    /// A v1 response body asking for `amount` of `asset` on Base Sepolia.
+    pub fn v1_body(amount: &str, asset: &str) -> String {
+        serde_json::json!({
+            "x402Version": 1,
+            "error": "X-PAYMENT header is required",
+            "accepts": [{
+                "scheme": "exact",
+                "network": "base-sepolia",
+                "maxAmountRequired": amount,
+                "resource": "http://localhost:8000/echo",
+                "description": "Echo endpoint",
+                "mimeType": "application/json",
+                "payTo": PAY_TO,
+                "maxTimeoutSeconds": 60,
+                "asset": asset,
+                "extra": {"name": "USDC", "version": "2"}
+            }]
+        })
+        .to_string()
+    }
+
+    pub fn v1_required(amount: &str, asset: &str) -> PaymentRequired {
+        PaymentRequired::parse(&HeaderMap::new(), v1_body(amount, asset).as_bytes()).unwrap()
+    }
+
+    /// A v2 `PAYMENT-REQUIRED` document accepting each
+    /// `(network, amount, asset)`.
+    pub fn v2_document(options: &[(&str, &str, &str)]) -> String {
+        let accepts: Vec<_> = options
+            .iter()
+            .map(|(network, amount, asset)| {
+                serde_json::json!({
+                    "scheme": "exact",
+                    "network": network,
+                    "amount": amount,
+                    "payTo": PAY_TO,
+                    "maxTimeoutSeconds": 60,
+                    "asset": asset,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "x402Version": 2,
+            "resource": {
+                "url": "https://api.example.com/data",
+                "description": "Data endpoint",
+                "mimeType": "application/json"
+            },
+            "accepts": accepts,
+        })
+        .to_string()
+    }
+
+    pub fn v2_required(options: &[(&str, &str, &str)]) -> PaymentRequired {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            PAYMENT_REQUIRED_HEADER,
+            HeaderValue::from_str(&BASE64.encode(v2_document(options))).unwrap(),
+        );
+        PaymentRequired::parse(&headers, b"").unwrap()
+    }
+}
@@ -0,0 +1,200 @@
+use crate::config::Policy;
+use crate::error::X402Error;
+use crate::payment::{PaymentOption, PaymentRequired};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+/// Match `text` against a glob where `*` matches any run of characters and
+/// `?` a single one. Hosts are case-insensitive, so matching is too.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let text: Vec<char> = text.to_ascii_lowercase().chars().collect();
+    // Position after the last `*`, and the text position it was matched at
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some((after_star, matched)) = star {
+            // Let the last `*` swallow one more character
+            p = after_star;
+            t = matched + 1;
+            star = Some((after_star, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// EVM addresses compare case-insensitively; Solana's base58 ones don't.
+fn same_address(a: &str, b: &str) -> bool {
+    if a.starts_with("0x") && b.starts_with("0x") {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+fn same_asset(entry: &str, option: &PaymentOption) -> bool {
+    same_address(entry, &option.asset) || entry.eq_ignore_ascii_case(option.symbol())
+}
+
+/// Whether `value` passes an allow/deny list pair.
+fn permitted(allow: &[String], deny: &[String], matches: impl Fn(&str) -> bool) -> bool {
+    !deny.iter().any(|entry| matches(entry))
+        && (allow.is_empty() || allow.iter().any(|entry| matches(entry)))
+}
+
+fn host_allowed(policy: &Policy, host: &str) -> bool {
+    permitted(&policy.allow_hosts, &policy.deny_hosts, |glob| {
+        glob_match(glob, host)
+    })
+}
+
+/// Why the policy rules out an option, if it does.
+fn violation(policy: &Policy, option: &PaymentOption) -> Option<String> {
+    if !permitted(&policy.allow_pay_to, &policy.deny_pay_to, |address| {
+        same_address(address, &option.pay_to)
+    }) {
+        return Some(format!("recipient {} is not allowed", option.pay_to));
+    }
+    if !permitted(&policy.allow_assets, &policy.deny_assets, |asset| {
+        same_asset(asset, option)
+    }) {
+        return Some(format!(
+            "asset {} on {} is not allowed",
+            option.asset, option.network
+        ));
+    }
+    None
+}
+
+/// Drop the payment options the policy rules out for a 402 response from
+/// `host`. Fails with [`X402Error::Payment`] if the host is not allowed or
+/// no option remains.
+pub fn apply(
+    policy: &Policy,
+    host: Option<&str>,
+    required: &mut PaymentRequired,
+) -> Result<(), X402Error> {
+    let host = host.unwrap_or_default();
+    if !host_allowed(policy, host) {
+        return Err(X402Error::Payment(format!(
+            "payment denied by policy: host {} is not allowed",
+            host
+        )));
+    }
+
+    let first = required.options.first().and_then(|o| violation(policy, o));
+    required.retain(|option| violation(policy, option).is_none());
+    if required.options.is_empty() {
+        return Err(X402Error::Payment(format!(
+            "payment denied by policy: {}",
+            first.unwrap_or_else(|| "no payment options".to_string())
+        )));
+    }
+    Ok(())
+}
+
+/// Record a payment the policy refused in `~/.x402/policy.jsonl`, one line
+/// of JSON per refusal, so denied attempts can be audited later.
+pub fn log_violation(url: &str, error: &X402Error) -> Result<(), X402Error> {
+    let path = dirs::home_dir()
+        .map(|h| h.join(".x402").join("policy.jsonl"))
+        .ok_or_else(|| X402Error::General("Cannot determine home directory".to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            X402Error::General(format!("Failed to create policy log directory: {}", e))
+        })?;
+    }
+
+    let line = serde_json::json!({
+        "timestamp": chrono::Utc::now(),
+        "url": url,
+        "reason": error.to_string(),
+    });
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| X402Error::General(format!("Failed to open policy log: {}", e)))?;
+    writeln!(file, "{}", line)
+        .map_err(|e| X402Error::General(format!("Failed to write policy log: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment::fixtures::{v1_required, PAY_TO, USDC};
+
+    /// Apply `policy` to a 402 response from `host`.
+    fn check(policy: &Policy, host: &str) -> Result<(), X402Error> {
+        apply(policy, Some(host), &mut v1_required("10000", USDC))
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.example.com", "api.example.com"));
+        assert!(glob_match("*.example.com", "a.b.Example.COM"));
+        assert!(!glob_match("*.example.com", "example.com"));
+        assert!(glob_match("api?.example.com", "api2.example.com"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("example.com", "example.com.evil"));
+    }
+
+    #[test]
+    fn test_empty_policy_allows() {
+        assert!(check(&Policy::default(), "api.example.com").is_ok());
+    }
+
+    #[test]
+    fn test_host_allowlist() {
+        let policy = Policy {
+            allow_hosts: strings(&["*.example.com"]),
+            ..Default::default()
+        };
+        assert!(check(&policy, "api.example.com").is_ok());
+        assert!(check(&policy, "example.org").is_err());
+    }
+
+    #[test]
+    fn test_deny_wins_over_allow() {
+        let policy = Policy {
+            allow_hosts: strings(&["*.example.com"]),
+            deny_hosts: strings(&["evil.example.com"]),
+            ..Default::default()
+        };
+        assert!(check(&policy, "evil.example.com").is_err());
+    }
+
+    #[test]
+    fn test_pay_to_and_asset_lists() {
+        let deny_recipient = Policy {
+            deny_pay_to: strings(&[&PAY_TO.to_lowercase()]),
+            ..Default::default()
+        };
+        let err = check(&deny_recipient, "api.example.com").unwrap_err();
+        assert!(err.to_string().contains("recipient"));
+
+        let allow_usdc = Policy {
+            allow_assets: strings(&["usdc"]),
+            ..Default::default()
+        };
+        assert!(check(&allow_usdc, "api.example.com").is_ok());
+
+        let allow_other = Policy {
+            allow_assets: strings(&["0x0000000000000000000000000000000000000001"]),
+            ..Default::default()
+        };
+        assert!(check(&allow_other, "api.example.com").is_err());
+    }
+}
@@ -1,32 +1,54 @@
+use crate::body::{BodySource, FileSource, FormField};
 use crate::cli::Args;
 use crate::error::X402Error;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use reqwest::multipart::{Form, Part};
-use reqwest::{Body, Method};
-use std::fs;
+use reqwest::Method;
 use std::str::FromStr;
 
 pub struct RequestConfig {
     pub method: Method,
     pub url: String,
     pub headers: HeaderMap,
-    pub body: Option<Body>,
+    pub body: Option<BodySource>,
     pub follow_redirects: bool,
 }
 
 impl RequestConfig {
     pub fn from_args(args: &Args) -> Result<Self, X402Error> {
-        let method = Method::from_str(&args.method.to_uppercase())
-            .map_err(|_| X402Error::General(format!("Invalid HTTP method: {}", args.method)))?;
+        // -I is HEAD and uploads are PUT unless a method is given, as with curl
+        let method = args.method.as_deref().unwrap_or(if args.head {
+            "HEAD"
+        } else if args.upload_file.is_some() {
+            "PUT"
+        } else {
+            "GET"
+        });
+        let method = Method::from_str(&method.to_uppercase())
+            .map_err(|_| X402Error::General(format!("Invalid HTTP method: {}", method)))?;
 
         let headers = Self::parse_headers(&args.headers)?;
-        let body = Self::parse_body(&args.data, &args.data_binary)?;
 
-        let url = args
+        let mut url = args
             .url
             .clone()
             .ok_or_else(|| X402Error::General("URL is required".into()))?;
 
+        // Form data takes precedence over body
+        let body = match (Self::parse_form(&args.form)?, &args.upload_file) {
+            (Some(form), _) => Some(form),
+            (None, Some(upload)) => {
+                let file = FileSource::open(upload)?;
+                // Uploading to a directory URL names the target after the file
+                if url.ends_with('/') {
+                    if let Some(name) = file.file_name() {
+                        url.push_str(name);
+                    }
+                }
+                Some(BodySource::File(file))
+            }
+            (None, None) => Self::parse_body(&args.data, &args.data_binary)?,
+        };
+
         Ok(RequestConfig {
             method,
             url,
@@ -58,29 +80,24 @@ impl RequestConfig {
     fn parse_body(
         data: &Option<String>,
         data_binary: &Option<String>,
-    ) -> Result<Option<Body>, X402Error> {
+    ) -> Result<Option<BodySource>, X402Error> {
         // data_binary takes precedence
         let data_str = data_binary.as_ref().or(data.as_ref());
 
         match data_str {
-            Some(d) if d.starts_with('@') => {
-                let path = &d[1..];
-                let content = fs::read(path).map_err(|e| {
-                    X402Error::General(format!("Failed to read file {}: {}", path, e))
-                })?;
-                Ok(Some(Body::from(content)))
-            }
-            Some(d) => Ok(Some(Body::from(d.clone()))),
+            // @- reads stdin, @path a file; either is streamed when sent
+            Some(d) if d.starts_with('@') => Ok(Some(BodySource::File(FileSource::open(&d[1..])?))),
+            Some(d) => Ok(Some(BodySource::Bytes(d.clone().into_bytes()))),
             None => Ok(None),
         }
     }
 
-    pub fn parse_form(form_fields: &[String]) -> Result<Option<Form>, X402Error> {
+    fn parse_form(form_fields: &[String]) -> Result<Option<BodySource>, X402Error> {
         if form_fields.is_empty() {
             return Ok(None);
         }
 
-        let mut form = Form::new();
+        let mut form = Vec::new();
         for field in form_fields {
             let parts: Vec<&str> = field.splitn(2, '=').collect();
             if parts.len() != 2 {
@@ -91,20 +108,17 @@ impl RequestConfig {
 
             if let Some(path) = value.strip_prefix('@') {
                 // File upload
-                let filename = std::path::Path::new(path)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("file")
-                    .to_string();
-                let content = std::fs::read(path).map_err(|e| {
-                    X402Error::General(format!("Failed to read file {}: {}", path, e))
-                })?;
-                let part = Part::bytes(content).file_name(filename);
-                form = form.part(name.to_string(), part);
+                form.push(FormField::File {
+                    name: name.to_string(),
+                    file: FileSource::open(path)?,
+                });
             } else {
-                form = form.text(name.to_string(), value.to_string());
+                form.push(FormField::Text {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
             }
         }
-        Ok(Some(form))
+        Ok(Some(BodySource::Form(form)))
     }
 }
@@ -0,0 +1,252 @@
+use crate::cli::KeyringCommand;
+use crate::error::X402Error;
+use alloy_signer_local::PrivateKeySigner;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+/// Service name under which keys are stored in the OS keyring.
+const SERVICE: &str = "x402curl";
+/// Prefix of a private key reference, e.g. `keyring:agent-wallet`.
+const REFERENCE_PREFIX: &str = "keyring:";
+
+/// Where named keys are stored.
+enum Backend {
+    /// OS keyring (Secret Service on Linux, Keychain on macOS). It can't be
+    /// enumerated, so stored names are tracked in `~/.x402/keyring-index`.
+    Os,
+    /// Plain JSON file of name -> key, selected with `X402_KEYRING_FILE`.
+    /// Meant for tests and headless machines without a keyring daemon.
+    File(PathBuf),
+}
+
+fn backend() -> Backend {
+    match std::env::var("X402_KEYRING_FILE") {
+        Ok(path) if !path.is_empty() => Backend::File(PathBuf::from(path)),
+        _ => Backend::Os,
+    }
+}
+
+fn keyring_error(e: keyring::Error) -> X402Error {
+    X402Error::Config(format!("Keyring error: {}", e))
+}
+
+fn index_path() -> Result<PathBuf, X402Error> {
+    dirs::home_dir()
+        .map(|h| h.join(".x402").join("keyring-index"))
+        .ok_or_else(|| X402Error::Config("Cannot determine home directory".to_string()))
+}
+
+fn read_index() -> Result<Vec<String>, X402Error> {
+    let path = index_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| X402Error::Config(format!("Failed to read keyring index: {}", e)))?;
+    Ok(content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.to_string())
+        .collect())
+}
+
+fn write_index(names: &[String]) -> Result<(), X402Error> {
+    let path = index_path()?;
+    write_private(&path, &names.join("\n"))
+}
+
+/// Write a file readable only by the owner.
+pub fn write_private(path: &Path, content: &str) -> Result<(), X402Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| X402Error::Config(format!("Failed to create directory: {}", e)))?;
+    }
+    fs::write(path, content)
+        .map_err(|e| X402Error::Config(format!("Failed to write {}: {}", path.display(), e)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| {
+            X402Error::Config(format!("Failed to restrict {}: {}", path.display(), e))
+        })?;
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<BTreeMap<String, String>, X402Error> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| X402Error::Config(format!("Failed to read keyring file: {}", e)))?;
+    serde_json::from_str(&content)
+        .map_err(|e| X402Error::Config(format!("Invalid keyring file: {}", e)))
+}
+
+fn write_file(path: &Path, keys: &BTreeMap<String, String>) -> Result<(), X402Error> {
+    let json = serde_json::to_string_pretty(keys)
+        .map_err(|e| X402Error::Config(format!("Failed to encode keyring file: {}", e)))?;
+    write_private(path, &json)
+}
+
+fn not_found(name: &str) -> X402Error {
+    X402Error::Config(format!("No key named '{}' in the keyring", name))
+}
+
+impl Backend {
+    fn store(&self, name: &str, key: &str) -> Result<(), X402Error> {
+        match self {
+            Backend::Os => {
+                keyring::Entry::new(SERVICE, name)
+                    .and_then(|entry| entry.set_password(key))
+                    .map_err(keyring_error)?;
+                let mut names = read_index()?;
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                    write_index(&names)?;
+                }
+                Ok(())
+            }
+            Backend::File(path) => {
+                let mut keys = read_file(path)?;
+                keys.insert(name.to_string(), key.to_string());
+                write_file(path, &keys)
+            }
+        }
+    }
+
+    fn load(&self, name: &str) -> Result<String, X402Error> {
+        match self {
+            Backend::Os => keyring::Entry::new(SERVICE, name)
+                .and_then(|entry| entry.get_password())
+                .map_err(|e| match e {
+                    keyring::Error::NoEntry => not_found(name),
+                    e => keyring_error(e),
+                }),
+            Backend::File(path) => read_file(path)?.remove(name).ok_or_else(|| not_found(name)),
+        }
+    }
+
+    fn remove(&self, name: &str) -> Result<(), X402Error> {
+        match self {
+            Backend::Os => {
+                keyring::Entry::new(SERVICE, name)
+                    .and_then(|entry| entry.delete_credential())
+                    .map_err(|e| match e {
+                        keyring::Error::NoEntry => not_found(name),
+                        e => keyring_error(e),
+                    })?;
+                let names: Vec<String> = read_index()?.into_iter().filter(|n| n != name).collect();
+                write_index(&names)
+            }
+            Backend::File(path) => {
+                let mut keys = read_file(path)?;
+                keys.remove(name).ok_or_else(|| not_found(name))?;
+                write_file(path, &keys)
+            }
+        }
+    }
+
+    fn names(&self) -> Result<Vec<String>, X402Error> {
+        match self {
+            Backend::Os => read_index(),
+            Backend::File(path) => Ok(read_file(path)?.into_keys().collect()),
+        }
+    }
+}
+
+/// Resolve a private key setting: `keyring:<name>` is looked up in the
+/// keyring, anything else is the key itself.
+pub fn resolve(value: &str) -> Result<String, X402Error> {
+    match value.strip_prefix(REFERENCE_PREFIX) {
+        Some(name) => backend().load(name),
+        None => Ok(value.to_string()),
+    }
+}
+
+fn parse_key(key: &str) -> Result<PrivateKeySigner, X402Error> {
+    key.trim()
+        .parse()
+        .map_err(|e| X402Error::Config(format!("Invalid private key: {}", e)))
+}
+
+fn validate_name(name: &str) -> Result<(), X402Error> {
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(X402Error::Config(format!("Invalid key name: {:?}", name)));
+    }
+    Ok(())
+}
+
+/// Run an `x402curl keyring` subcommand.
+pub fn run(command: &KeyringCommand) -> Result<(), X402Error> {
+    let backend = backend();
+    match command {
+        KeyringCommand::Import { name } => {
+            validate_name(name)?;
+            let mut key = String::new();
+            io::stdin()
+                .lock()
+                .read_line(&mut key)
+                .map_err(|e| X402Error::General(format!("Failed to read key: {}", e)))?;
+            let signer = parse_key(&key)?;
+            backend.store(name, key.trim())?;
+            println!("Stored {} ({})", name, signer.address().to_checksum(None));
+            println!("Use it with: --x402-key {}{}", REFERENCE_PREFIX, name);
+        }
+        KeyringCommand::List => {
+            let names = backend.names()?;
+            if names.is_empty() {
+                println!("No keys stored.");
+            }
+            for name in names {
+                let address = backend
+                    .load(&name)
+                    .and_then(|key| parse_key(&key))
+                    .map_or_else(|e| format!("<{}>", e), |s| s.address().to_checksum(None));
+                println!("{:<24}  {}", name, address);
+            }
+        }
+        KeyringCommand::Remove { name } => {
+            backend.remove(name)?;
+            println!("Removed {}", name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[test]
+    fn test_file_backend_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Backend::File(dir.path().join("keys.json"));
+
+        backend.store("agent", KEY).unwrap();
+        backend.store("other", KEY).unwrap();
+        assert_eq!(backend.load("agent").unwrap(), KEY);
+        assert_eq!(backend.names().unwrap(), ["agent", "other"]);
+
+        backend.remove("agent").unwrap();
+        assert!(backend.load("agent").is_err());
+        assert!(backend.remove("agent").is_err());
+        assert_eq!(backend.names().unwrap(), ["other"]);
+    }
+
+    #[test]
+    fn test_resolve_plain_key() {
+        assert_eq!(resolve(KEY).unwrap(), KEY);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("agent-wallet").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("two words").is_err());
+    }
+}
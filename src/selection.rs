@@ -0,0 +1,154 @@
+use crate::budget::to_budget_units;
+use crate::chains;
+use crate::config::Selection;
+use crate::payment::{PaymentOption, PaymentRequired};
+
+/// Whether two x402 network identifiers name the same network, so that
+/// "base" matches "eip155:8453".
+fn same_network(a: &str, b: &str) -> bool {
+    match (chains::by_network(a), chains::by_network(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+fn network_rank(selection: &Selection, option: &PaymentOption) -> usize {
+    selection
+        .networks
+        .iter()
+        .position(|n| same_network(n, &option.network))
+        .unwrap_or(selection.networks.len())
+}
+
+fn asset_rank(selection: &Selection, option: &PaymentOption) -> usize {
+    selection
+        .assets
+        .iter()
+        .position(|a| {
+            a.eq_ignore_ascii_case(&option.asset) || a.eq_ignore_ascii_case(option.symbol())
+        })
+        .unwrap_or(selection.assets.len())
+}
+
+/// Cost in budget units when ordering by price; unknown assets sort last.
+fn cost(selection: &Selection, option: &PaymentOption) -> u128 {
+    if !selection.cheapest {
+        return 0;
+    }
+    to_budget_units(option.amount, &option.network, &option.asset).unwrap_or(u128::MAX)
+}
+
+/// Reorder the payment options so the preferred one comes first; the x402
+/// client pays with the first option it supports. Network preference wins
+/// over asset preference, which wins over price. Ties keep the server's order.
+pub fn sort(selection: &Selection, required: &mut PaymentRequired) {
+    required.sort_by_key(|option| {
+        (
+            network_rank(selection, option),
+            asset_rank(selection, option),
+            cost(selection, option),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment::fixtures::v2_required;
+
+    fn required() -> PaymentRequired {
+        v2_required(&[
+            (
+                "eip155:8453",
+                "20000",
+                "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            ),
+            (
+                "eip155:137",
+                "10000",
+                "0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359",
+            ),
+            (
+                "eip155:10",
+                "5000",
+                "0x0000000000000000000000000000000000000001",
+            ),
+        ])
+    }
+
+    fn networks(required: &PaymentRequired) -> Vec<&str> {
+        required
+            .options
+            .iter()
+            .map(|o| o.network.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_keeps_server_order() {
+        let mut required = required();
+        sort(&Selection::default(), &mut required);
+        assert_eq!(
+            networks(&required),
+            ["eip155:8453", "eip155:137", "eip155:10"]
+        );
+    }
+
+    #[test]
+    fn test_preferred_networks_by_name() {
+        let selection = Selection {
+            networks: vec!["optimism".to_string(), "polygon".to_string()],
+            ..Default::default()
+        };
+        let mut required = required();
+        sort(&selection, &mut required);
+        assert_eq!(
+            networks(&required),
+            ["eip155:10", "eip155:137", "eip155:8453"]
+        );
+    }
+
+    #[test]
+    fn test_preferred_asset_symbol() {
+        let selection = Selection {
+            assets: vec!["usdc".to_string()],
+            ..Default::default()
+        };
+        let mut required = required();
+        required.options.reverse();
+        sort(&selection, &mut required);
+        assert_eq!(
+            networks(&required),
+            ["eip155:137", "eip155:8453", "eip155:10"]
+        );
+    }
+
+    #[test]
+    fn test_cheapest_first_unknown_asset_last() {
+        let selection = Selection {
+            cheapest: true,
+            ..Default::default()
+        };
+        let mut required = required();
+        sort(&selection, &mut required);
+        assert_eq!(
+            networks(&required),
+            ["eip155:137", "eip155:8453", "eip155:10"]
+        );
+    }
+
+    #[test]
+    fn test_network_preference_beats_price() {
+        let selection = Selection {
+            networks: vec!["base".to_string()],
+            cheapest: true,
+            ..Default::default()
+        };
+        let mut required = required();
+        sort(&selection, &mut required);
+        assert_eq!(
+            networks(&required),
+            ["eip155:8453", "eip155:137", "eip155:10"]
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use crate::error::X402Error;
+use alloy_primitives::{hex, Address, ChainId, Signature, B256, I256, U256};
+use alloy_signer::{Error as SignerError, Signer, UnsupportedSignerOperation};
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::{Eip712Domain, SolStruct};
+#[cfg(unix)]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// JSON-RPC method returning the accounts the signing daemon holds.
+const ACCOUNTS_METHOD: &str = "eth_accounts";
+/// JSON-RPC method asking the signing daemon to sign EIP-712 typed data, as
+/// named by Clef.
+const SIGN_TYPED_DATA_METHOD: &str = "account_signTypedData";
+
+/// The key that signs EVM payments.
+pub enum EvmSigner {
+    /// Private key held in memory (raw key or decrypted keystore)
+    Local(PrivateKeySigner),
+    /// Key held by an external signing daemon
+    Remote(RemoteSigner),
+}
+
+impl EvmSigner {
+    pub fn address(&self) -> Address {
+        match self {
+            EvmSigner::Local(signer) => signer.address(),
+            EvmSigner::Remote(signer) => signer.address,
+        }
+    }
+}
+
+/// How to reach the signing daemon.
+#[derive(Debug, Clone)]
+enum Endpoint {
+    /// JSON-RPC over HTTP(S)
+    Http(String),
+    /// Newline-delimited JSON-RPC over a Unix socket
+    #[cfg(unix)]
+    Unix(String),
+}
+
+/// Signer that forwards EIP-712 signing requests to a local daemon, so the
+/// private key never enters x402curl's memory.
+///
+/// The daemon speaks JSON-RPC 2.0, over HTTP or as one request per line on a
+/// Unix socket, as Clef does. `eth_accounts` returns the signing address (the
+/// first one is used); `account_signTypedData` is called with the address and
+/// the EIP-712 typed data `{types, primaryType, domain, message}`, and
+/// returns a 65-byte hex signature.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    endpoint: Endpoint,
+    address: Address,
+    chain_id: Option<ChainId>,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    /// Connect to the daemon at `url` (`http(s)://...` or `unix:///path`)
+    /// and fetch its signing address.
+    pub async fn connect(url: &str) -> Result<Self, X402Error> {
+        let endpoint = match url.strip_prefix("unix://") {
+            #[cfg(unix)]
+            Some(path) => Endpoint::Unix(path.to_string()),
+            #[cfg(not(unix))]
+            Some(_) => {
+                return Err(X402Error::Config(
+                    "Unix socket signers are only supported on Unix; use http(s)://host:port"
+                        .to_string(),
+                ))
+            }
+            None if url.starts_with("http://") || url.starts_with("https://") => {
+                Endpoint::Http(url.to_string())
+            }
+            None => {
+                return Err(X402Error::Config(format!(
+                    "Invalid signer URL: {}. Use http(s)://host:port or unix:///path/to/socket.",
+                    url
+                )))
+            }
+        };
+        let mut signer = RemoteSigner {
+            endpoint,
+            address: Address::ZERO,
+            chain_id: None,
+            client: reqwest::Client::new(),
+        };
+
+        let accounts = signer.call(ACCOUNTS_METHOD, serde_json::json!([])).await?;
+        signer.address = accounts
+            .as_array()
+            .and_then(|a| a.first())
+            .and_then(|a| a.as_str())
+            .and_then(|a| a.parse().ok())
+            .ok_or_else(|| X402Error::Config("Signer returned no accounts".to_string()))?;
+        Ok(signer)
+    }
+
+    /// Send a JSON-RPC request to the daemon and return its `result`.
+    async fn call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, X402Error> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1
+        });
+        let unavailable =
+            |e: &dyn std::fmt::Display| X402Error::Config(format!("Failed to reach signer: {}", e));
+
+        let mut response: serde_json::Value = match self.endpoint {
+            Endpoint::Http(ref url) => self
+                .client
+                .post(url)
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| unavailable(&e))?
+                .json()
+                .await
+                .map_err(|e| unavailable(&e))?,
+            #[cfg(unix)]
+            Endpoint::Unix(ref path) => {
+                let mut stream = tokio::net::UnixStream::connect(path)
+                    .await
+                    .map_err(|e| unavailable(&e))?;
+                let mut line = request.to_string();
+                line.push('\n');
+                stream
+                    .write_all(line.as_bytes())
+                    .await
+                    .map_err(|e| unavailable(&e))?;
+                let mut reply = String::new();
+                BufReader::new(stream)
+                    .read_line(&mut reply)
+                    .await
+                    .map_err(|e| unavailable(&e))?;
+                serde_json::from_str(&reply).map_err(|e| unavailable(&e))?
+            }
+        };
+
+        if let Some(err) = response.get("error") {
+            return Err(X402Error::Payment(format!("Signer refused: {}", err)));
+        }
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(unavailable(&"missing result in response")),
+        }
+    }
+}
+
+/// The `(type, name)` fields of the first struct in an EIP-712 type
+/// encoding, e.g. `Mail(address from,string contents)`.
+fn fields(encode_type: &str) -> Vec<(&str, &str)> {
+    let start = encode_type.find('(').map_or(0, |i| i + 1);
+    let end = encode_type.find(')').unwrap_or(encode_type.len());
+    encode_type[start..end]
+        .split(',')
+        .filter_map(|field| field.split_once(' '))
+        .collect()
+}
+
+fn type_json(encode_type: &str) -> serde_json::Value {
+    fields(encode_type)
+        .into_iter()
+        .map(|(ty, name)| serde_json::json!({"name": name, "type": ty}))
+        .collect()
+}
+
+/// A field's value from its 32-byte EIP-712 encoding. Only atomic types can
+/// be decoded: strings, bytes, arrays and structs are encoded as hashes.
+fn decode_word(ty: &str, word: &[u8]) -> Option<serde_json::Value> {
+    if ty.contains('[') || word.len() != 32 {
+        return None;
+    }
+    Some(match ty {
+        "address" => Address::from_slice(&word[12..]).to_checksum(None).into(),
+        "bool" => (word[31] != 0).into(),
+        _ if ty.starts_with("uint") => U256::from_be_slice(word).to_string().into(),
+        _ if ty.starts_with("int") => I256::from_raw(U256::from_be_slice(word)).to_string().into(),
+        _ => {
+            let len: usize = ty.strip_prefix("bytes")?.parse().ok()?;
+            hex::encode_prefixed(word.get(..len)?).into()
+        }
+    })
+}
+
+/// Typed data for an `account_signTypedData` request, which the daemon
+/// hashes itself so it knows what it signs.
+fn typed_data<T: SolStruct>(
+    payload: &T,
+    domain: &Eip712Domain,
+) -> Result<serde_json::Value, X402Error> {
+    let encode_type = payload.eip712_encode_type();
+    let data = payload.eip712_encode_data();
+    let mut message = serde_json::Map::new();
+    for ((ty, name), word) in fields(&encode_type).into_iter().zip(data.chunks(32)) {
+        let value = decode_word(ty, word).ok_or_else(|| {
+            X402Error::Payment(format!(
+                "Cannot send {} to the signer: field {} has unsupported type {}",
+                T::NAME,
+                name,
+                ty
+            ))
+        })?;
+        message.insert(name.to_string(), value);
+    }
+
+    let mut types = serde_json::Map::new();
+    types.insert("EIP712Domain".to_string(), type_json(&domain.encode_type()));
+    types.insert(T::NAME.to_string(), type_json(&encode_type));
+    Ok(serde_json::json!({
+        "types": types,
+        "primaryType": T::NAME,
+        "domain": domain,
+        "message": message,
+    }))
+}
+
+#[async_trait::async_trait]
+impl Signer for RemoteSigner {
+    /// Raw hashes are never signed: the daemon must see what it signs.
+    async fn sign_hash(&self, _hash: &B256) -> alloy_signer::Result<Signature> {
+        Err(SignerError::UnsupportedOperation(
+            UnsupportedSignerOperation::SignHash,
+        ))
+    }
+
+    async fn sign_typed_data<T: SolStruct + Send + Sync>(
+        &self,
+        payload: &T,
+        domain: &Eip712Domain,
+    ) -> alloy_signer::Result<Signature> {
+        let typed_data = typed_data(payload, domain).map_err(SignerError::other)?;
+        let params = serde_json::json!([self.address.to_checksum(None), typed_data]);
+        let result = self
+            .call(SIGN_TYPED_DATA_METHOD, params)
+            .await
+            .map_err(SignerError::other)?;
+        let signature = result
+            .as_str()
+            .ok_or_else(|| SignerError::message("Signer returned no signature"))?;
+        signature.parse().map_err(SignerError::other)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_sol_types::{eip712_domain, sol};
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    sol! {
+        struct TransferWithAuthorization {
+            address from;
+            address to;
+            uint256 value;
+            uint256 validAfter;
+            uint256 validBefore;
+            bytes32 nonce;
+        }
+    }
+
+    const ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    /// Mock signing daemon holding the well-known Anvil test key.
+    async fn mock_signer() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"method": "eth_accounts"}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "result": [ADDRESS]
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn authorization() -> (TransferWithAuthorization, Eip712Domain) {
+        let payload = TransferWithAuthorization {
+            from: ADDRESS.parse().unwrap(),
+            to: "0x209693Bc6afc0C5328bA36FaF03C514EF312287C"
+                .parse()
+                .unwrap(),
+            value: alloy_primitives::U256::from(10_000),
+            validAfter: alloy_primitives::U256::ZERO,
+            validBefore: alloy_primitives::U256::from(u64::MAX),
+            nonce: B256::repeat_byte(7),
+        };
+        let domain = eip712_domain! {
+            name: "USDC",
+            version: "2",
+            chain_id: 84532,
+            verifying_contract: alloy_primitives::address!("036CbD53842c5426634e7929541eC2318f3dCF7e"),
+        };
+        (payload, domain)
+    }
+
+    #[tokio::test]
+    async fn test_connect_fetches_address() {
+        let server = mock_signer().await;
+        let signer = RemoteSigner::connect(&server.uri()).await.unwrap();
+        assert_eq!(signer.address(), ADDRESS.parse::<Address>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_url() {
+        assert!(RemoteSigner::connect("ftp://localhost").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sign_typed_data_round_trip() {
+        let (payload, domain) = authorization();
+        let hash = payload.eip712_signing_hash(&domain);
+        let key: PrivateKeySigner =
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                .parse()
+                .unwrap();
+        let expected = key.sign_hash(&hash).await.unwrap();
+
+        let server = mock_signer().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "method": "account_signTypedData",
+                "params": [ADDRESS, {
+                    "types": {
+                        "EIP712Domain": [
+                            {"name": "name", "type": "string"},
+                            {"name": "version", "type": "string"},
+                            {"name": "chainId", "type": "uint256"},
+                            {"name": "verifyingContract", "type": "address"},
+                        ],
+                        "TransferWithAuthorization": [
+                            {"name": "from", "type": "address"},
+                            {"name": "to", "type": "address"},
+                            {"name": "value", "type": "uint256"},
+                            {"name": "validAfter", "type": "uint256"},
+                            {"name": "validBefore", "type": "uint256"},
+                            {"name": "nonce", "type": "bytes32"},
+                        ],
+                    },
+                    "primaryType": "TransferWithAuthorization",
+                    "domain": {"name": "USDC", "version": "2"},
+                    "message": {
+                        "from": ADDRESS,
+                        "to": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+                        "value": "10000",
+                        "validAfter": "0",
+                        "validBefore": u64::MAX.to_string(),
+                        "nonce": B256::repeat_byte(7),
+                    },
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "result": expected.to_string()
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let signer = RemoteSigner::connect(&server.uri()).await.unwrap();
+        let signature = signer.sign_typed_data(&payload, &domain).await.unwrap();
+        assert_eq!(signature, expected);
+        assert_eq!(
+            signature.recover_address_from_prehash(&hash).unwrap(),
+            signer.address()
+        );
+    }
+
+    #[test]
+    fn test_decode_word() {
+        let mut word = [0u8; 32];
+        word[31] = 1;
+        assert_eq!(decode_word("bool", &word), Some(true.into()));
+        assert_eq!(decode_word("uint8", &word), Some("1".into()));
+        assert_eq!(decode_word("bytes1", &word), Some("0x00".into()));
+        assert_eq!(decode_word("int256", &[0xff; 32]), Some("-1".into()));
+        // Hashed, so the value can't be recovered
+        assert_eq!(decode_word("string", &word), None);
+        assert_eq!(decode_word("uint256[]", &word), None);
+    }
+
+    #[tokio::test]
+    async fn test_sign_hash_refused() {
+        let server = mock_signer().await;
+        let signer = RemoteSigner::connect(&server.uri()).await.unwrap();
+        assert!(signer.sign_hash(&B256::ZERO).await.is_err());
+    }
+}
@@ -0,0 +1,120 @@
+/// Incremental parser for `text/event-stream` bodies, following the
+/// WHATWG event stream format. Feed it chunks as they arrive; it returns the
+/// data of each event once the blank line ending it has been seen.
+#[derive(Debug, Default)]
+pub struct Parser {
+    /// Bytes of the line being read
+    line: Vec<u8>,
+    /// Data lines of the event being read
+    data: Vec<String>,
+    /// The previous chunk ended in `\r`, so a leading `\n` belongs to it
+    after_cr: bool,
+    /// At the start of the stream, where a byte order mark is skipped
+    started: bool,
+}
+
+impl Parser {
+    /// Parse the next chunk of the stream, returning the data of the events
+    /// it completes. An event without a terminating blank line is kept until
+    /// one arrives, and dropped if the stream ends first.
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Vec<String> {
+        if !self.started && !chunk.is_empty() {
+            self.started = true;
+            chunk = chunk.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(chunk);
+        }
+
+        let mut events = Vec::new();
+        for &byte in chunk {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            match byte {
+                b'\n' if after_cr => {}
+                b'\n' | b'\r' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(data) = self.process_line(&String::from_utf8_lossy(&line)) {
+                        events.push(data);
+                    }
+                }
+                _ => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                return None;
+            }
+            return Some(std::mem::take(&mut self.data).join("\n"));
+        }
+        if line.starts_with(':') {
+            // Comment, often sent as a keep-alive
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+        None
+    }
+}
+
+/// Render an event's data as one line of JSON: the data itself when it is
+/// JSON, otherwise the data as a JSON string (e.g. OpenAI's `[DONE]`).
+pub fn to_json_line(data: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(data) {
+        Ok(value) => value.to_string(),
+        Err(_) => serde_json::Value::String(data.to_string()).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Vec<String> {
+        let mut parser = Parser::default();
+        chunks
+            .iter()
+            .flat_map(|chunk| parser.feed(chunk.as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_events_and_fields() {
+        let stream =
+            "event: delta\ndata: {\"text\":\"hi\"}\nid: 1\n\n: keep-alive\n\ndata:a\ndata: b\n\n";
+        assert_eq!(parse(&[stream]), vec!["{\"text\":\"hi\"}", "a\nb"]);
+    }
+
+    #[test]
+    fn test_event_split_across_chunks() {
+        assert_eq!(parse(&["da", "ta: hel", "lo\n", "\n"]), vec!["hello"]);
+        assert_eq!(parse(&["data: x\r", "\n\r", "\n"]), vec!["x"]);
+    }
+
+    #[test]
+    fn test_line_endings_and_bom() {
+        assert_eq!(
+            parse(&["\u{feff}data: x\r\rdata: y\r\n\r\n"]),
+            vec!["x", "y"]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_event_dropped() {
+        assert!(parse(&["data: partial\n"]).is_empty());
+        assert!(parse(&["event: ping\n\n"]).is_empty());
+    }
+
+    #[test]
+    fn test_to_json_line() {
+        assert_eq!(to_json_line("{ \"a\": 1 }"), "{\"a\":1}");
+        assert_eq!(to_json_line("[DONE]"), "\"[DONE]\"");
+        assert_eq!(to_json_line("two\nlines"), "\"two\\nlines\"");
+    }
+}
@@ -0,0 +1,349 @@
+use crate::cli::WalletCommand;
+use crate::error::X402Error;
+use crate::secrets;
+use alloy_primitives::Address;
+use alloy_signer_local::PrivateKeySigner;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the keystore password.
+const PASSWORD_ENV: &str = "X402_WALLET_PASSWORD";
+/// Environment variable holding the new password for `wallet rekey`.
+const NEW_PASSWORD_ENV: &str = "X402_WALLET_NEW_PASSWORD";
+
+/// Directory holding named keystores, `~/.x402/wallets`.
+fn wallets_dir() -> Result<PathBuf, X402Error> {
+    dirs::home_dir()
+        .map(|h| h.join(".x402").join("wallets"))
+        .ok_or_else(|| X402Error::Config("Cannot determine home directory".to_string()))
+}
+
+fn wallet_file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.json", name))
+}
+
+/// Resolve a `--x402-wallet` value: an existing path is used as is,
+/// otherwise a wallet of that name in `~/.x402/wallets` is tried.
+pub fn resolve_path(value: &str) -> PathBuf {
+    let path = PathBuf::from(value);
+    if path.exists() {
+        return path;
+    }
+    match wallets_dir() {
+        Ok(dir) if validate_name(value).is_ok() && wallet_file(&dir, value).exists() => {
+            wallet_file(&dir, value)
+        }
+        _ => path,
+    }
+}
+
+/// Wallet names become file names, so keep them to a safe character set.
+fn validate_name(name: &str) -> Result<(), X402Error> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(X402Error::Config(format!(
+            "Invalid wallet name: {:?}. Use letters, digits, '-', '_' and '.'.",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn decrypt(path: &Path, password: &str) -> Result<PrivateKeySigner, X402Error> {
+    PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+        X402Error::Config(format!(
+            "Failed to decrypt keystore '{}': {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Encrypt `signer` into a Keystore v3 file at `path`, readable only by the
+/// owner. The address is recorded in the file, as geth does, so it can be
+/// shown without the password.
+fn write_keystore(path: &Path, signer: &PrivateKeySigner, password: &str) -> Result<(), X402Error> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| X402Error::Config(format!("Invalid keystore path: {}", path.display())))?;
+    fs::create_dir_all(dir)
+        .map_err(|e| X402Error::Config(format!("Failed to create directory: {}", e)))?;
+    PrivateKeySigner::encrypt_keystore(
+        dir,
+        &mut rand::thread_rng(),
+        signer.to_bytes(),
+        password,
+        Some(file_name),
+    )
+    .map_err(|e| X402Error::Config(format!("Failed to encrypt keystore: {}", e)))?;
+
+    let mut keystore = read_keystore(path)?;
+    keystore["address"] =
+        serde_json::Value::String(alloy_primitives::hex::encode(signer.address().as_slice()));
+    secrets::write_private(path, &keystore.to_string())
+}
+
+fn read_keystore(path: &Path) -> Result<serde_json::Value, X402Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        X402Error::Config(format!(
+            "Failed to read keystore '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    serde_json::from_str(&content)
+        .map_err(|e| X402Error::Config(format!("Invalid keystore '{}': {}", path.display(), e)))
+}
+
+/// Path of an existing wallet, failing if there is none of that name.
+fn existing(dir: &Path, name: &str) -> Result<PathBuf, X402Error> {
+    validate_name(name)?;
+    let path = wallet_file(dir, name);
+    if !path.exists() {
+        return Err(X402Error::Config(format!("No wallet named '{}'", name)));
+    }
+    Ok(path)
+}
+
+/// Save `signer` as a new wallet, refusing to overwrite an existing one.
+fn create(
+    dir: &Path,
+    name: &str,
+    signer: &PrivateKeySigner,
+    password: &str,
+) -> Result<PathBuf, X402Error> {
+    validate_name(name)?;
+    if password.is_empty() {
+        return Err(X402Error::Config(
+            "Wallet password must not be empty".to_string(),
+        ));
+    }
+    let path = wallet_file(dir, name);
+    if path.exists() {
+        return Err(X402Error::Config(format!(
+            "Wallet '{}' already exists: {}",
+            name,
+            path.display()
+        )));
+    }
+    write_keystore(&path, signer, password)?;
+    Ok(path)
+}
+
+/// Address of a wallet: read from the keystore's `address` field, or by
+/// decrypting it when the field is missing and a password is given.
+fn address(dir: &Path, name: &str, password: Option<&str>) -> Result<Address, X402Error> {
+    let path = existing(dir, name)?;
+    let recorded = read_keystore(&path)?
+        .get("address")
+        .and_then(|a| a.as_str())
+        .and_then(|a| a.parse().ok());
+    match (recorded, password) {
+        (Some(address), _) => Ok(address),
+        (None, Some(password)) => decrypt(&path, password).map(|s| s.address()),
+        (None, None) => Err(X402Error::Config(format!(
+            "Keystore '{}' does not record its address. Pass --password to decrypt it.",
+            path.display()
+        ))),
+    }
+}
+
+/// Re-encrypt a wallet under a new password. The new keystore is written
+/// next to the old one and renamed over it, so a failure leaves it intact.
+fn rekey(dir: &Path, name: &str, password: &str, new_password: &str) -> Result<Address, X402Error> {
+    let path = existing(dir, name)?;
+    if new_password.is_empty() {
+        return Err(X402Error::Config(
+            "Wallet password must not be empty".to_string(),
+        ));
+    }
+    let signer = decrypt(&path, password)?;
+    let staged = dir.join(format!(".{}.json.tmp", name));
+    write_keystore(&staged, &signer, new_password)?;
+    fs::rename(&staged, &path)
+        .map_err(|e| X402Error::Config(format!("Failed to replace keystore: {}", e)))?;
+    Ok(signer.address())
+}
+
+/// A password from the command line, falling back to an environment variable.
+fn password(flag: &Option<String>, env: &str) -> Option<String> {
+    flag.clone()
+        .or_else(|| std::env::var(env).ok().filter(|p| !p.is_empty()))
+}
+
+/// Ask for a password on the terminal without echoing it. Returns `None`
+/// when not running interactively, so scripts fail instead of hanging.
+pub fn prompt_password(prompt: &str) -> Result<Option<String>, X402Error> {
+    if !io::stderr().is_terminal() {
+        return Ok(None);
+    }
+    rpassword::prompt_password(prompt)
+        .map(Some)
+        .map_err(|e| X402Error::Config(format!("Failed to read password: {}", e)))
+}
+
+/// Read a password from a file, ignoring the trailing newline.
+pub fn read_password_file(path: &Path) -> Result<String, X402Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        X402Error::Config(format!(
+            "Failed to read password file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// A password from the command line or environment, otherwise prompted for.
+/// A new password is asked for twice.
+fn require_password(
+    flag: &Option<String>,
+    env: &str,
+    option: &str,
+    prompt: &str,
+    new: bool,
+) -> Result<String, X402Error> {
+    if let Some(password) = password(flag, env) {
+        return Ok(password);
+    }
+    let password = prompt_password(prompt)?.ok_or_else(|| {
+        X402Error::Config(format!(
+            "No password provided. Use {} or set {}.",
+            option, env
+        ))
+    })?;
+    if new && prompt_password("Repeat password: ")?.as_deref() != Some(password.as_str()) {
+        return Err(X402Error::Config("Passwords do not match".to_string()));
+    }
+    Ok(password)
+}
+
+fn print_created(name: &str, path: &Path, address: Address) {
+    println!("Created {} ({})", name, address.to_checksum(None));
+    println!("Saved to {}", path.display());
+    println!("Use it with: --x402-wallet {}", name);
+}
+
+/// Run an `x402curl wallet` subcommand.
+pub fn run(command: &WalletCommand) -> Result<(), X402Error> {
+    let dir = wallets_dir()?;
+    match command {
+        WalletCommand::New { name, password } => {
+            let password =
+                require_password(password, PASSWORD_ENV, "--password", "New password: ", true)?;
+            let signer = PrivateKeySigner::random();
+            let path = create(&dir, name, &signer, &password)?;
+            print_created(name, &path, signer.address());
+        }
+        WalletCommand::Import { name, password } => {
+            let password =
+                require_password(password, PASSWORD_ENV, "--password", "New password: ", true)?;
+            let mut key = String::new();
+            io::stdin()
+                .lock()
+                .read_line(&mut key)
+                .map_err(|e| X402Error::General(format!("Failed to read key: {}", e)))?;
+            let signer: PrivateKeySigner = secrets::resolve(key.trim())?
+                .trim()
+                .parse()
+                .map_err(|e| X402Error::Config(format!("Invalid private key: {}", e)))?;
+            let path = create(&dir, name, &signer, &password)?;
+            print_created(name, &path, signer.address());
+        }
+        WalletCommand::Address { name, password } => {
+            let password = self::password(password, PASSWORD_ENV);
+            let address = address(&dir, name, password.as_deref())?;
+            println!("{}", address.to_checksum(None));
+        }
+        WalletCommand::Rekey {
+            name,
+            password,
+            new_password,
+        } => {
+            let password =
+                require_password(password, PASSWORD_ENV, "--password", "Password: ", false)?;
+            let new_password = require_password(
+                new_password,
+                NEW_PASSWORD_ENV,
+                "--new-password",
+                "New password: ",
+                true,
+            )?;
+            let address = rekey(&dir, name, &password, &new_password)?;
+            println!(
+                "Changed password of {} ({})",
+                name,
+                address.to_checksum(None)
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    #[test]
+    fn test_create_address_rekey() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer: PrivateKeySigner = KEY.parse().unwrap();
+
+        let path = create(dir.path(), "agent", &signer, "old-password").unwrap();
+        assert_eq!(path, dir.path().join("agent.json"));
+        assert_eq!(
+            address(dir.path(), "agent", None).unwrap(),
+            ADDRESS.parse::<Address>().unwrap()
+        );
+
+        rekey(dir.path(), "agent", "old-password", "new-password").unwrap();
+        assert!(decrypt(&path, "old-password").is_err());
+        assert_eq!(
+            decrypt(&path, "new-password").unwrap().address(),
+            signer.address()
+        );
+        assert!(!dir.path().join(".agent.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_create_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = PrivateKeySigner::random();
+        create(dir.path(), "agent", &signer, "password").unwrap();
+        assert!(create(dir.path(), "agent", &signer, "password").is_err());
+    }
+
+    #[test]
+    fn test_rekey_wrong_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let signer = PrivateKeySigner::random();
+        create(dir.path(), "agent", &signer, "password").unwrap();
+        assert!(rekey(dir.path(), "agent", "wrong", "new-password").is_err());
+        assert!(decrypt(&dir.path().join("agent.json"), "password").is_ok());
+    }
+
+    #[test]
+    fn test_read_password_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), "  pass word \n").unwrap();
+        assert_eq!(read_password_file(file.path()).unwrap(), "  pass word ");
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("agent-wallet_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../escape").is_err());
+        assert!(validate_name(".hidden").is_err());
+    }
+}
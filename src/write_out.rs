@@ -0,0 +1,238 @@
+use crate::error::X402Error;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Response, Version};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+/// What `-w` can report about a finished request.
+#[derive(Debug, Default)]
+pub struct Transfer {
+    pub method: String,
+    /// URL of the final response, after redirects
+    pub url_effective: String,
+    pub http_code: u16,
+    pub http_version: &'static str,
+    pub headers: HeaderMap,
+    /// Bytes of response body received
+    pub size_download: u64,
+    pub time_total: Duration,
+    /// Amount paid in token units (e.g. "0.010000"); unset if nothing was paid
+    pub x402_amount: Option<String>,
+    pub x402_network: Option<String>,
+    pub x402_tx_hash: Option<String>,
+}
+
+impl Transfer {
+    pub fn new(method: &str, response: &Response) -> Self {
+        Transfer {
+            method: method.to_string(),
+            url_effective: response.url().to_string(),
+            http_code: response.status().as_u16(),
+            http_version: match response.version() {
+                Version::HTTP_09 => "0.9",
+                Version::HTTP_10 => "1.0",
+                Version::HTTP_2 => "2",
+                Version::HTTP_3 => "3",
+                _ => "1.1",
+            },
+            headers: response.headers().clone(),
+            ..Default::default()
+        }
+    }
+
+    /// All values of a header, comma separated.
+    fn header(&self, name: &str) -> String {
+        self.headers
+            .get_all(name)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn variable(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "content_type" => self.header(CONTENT_TYPE.as_str()),
+            "http_code" | "response_code" => format!("{:03}", self.http_code),
+            "http_version" => self.http_version.to_string(),
+            "method" => self.method.clone(),
+            "num_headers" => self.headers.len().to_string(),
+            "size_download" => self.size_download.to_string(),
+            "time_total" => format!("{:.6}", self.time_total.as_secs_f64()),
+            "url_effective" => self.url_effective.clone(),
+            "x402_paid" => (self.x402_amount.is_some() as u8).to_string(),
+            "x402_amount" => self.x402_amount.clone().unwrap_or_default(),
+            "x402_network" => self.x402_network.clone().unwrap_or_default(),
+            "x402_tx_hash" => self.x402_tx_hash.clone().unwrap_or_default(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Expand a `-w` format as curl does: `%{variable}`, `%header{name}`, `%%`
+/// and the escapes `\n`, `\r` and `\t`. `%{stdout}` and `%{stderr}` switch
+/// where the rest goes; the text for each stream is returned in order.
+fn render(format: &str, transfer: &Transfer) -> Vec<(Stream, String)> {
+    let mut parts = vec![(Stream::Stdout, String::new())];
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        let text = &mut parts.last_mut().unwrap().1;
+        if let Some(after) = rest.strip_prefix("%{") {
+            if let Some(end) = after.find('}') {
+                let name = &after[..end];
+                rest = &after[end + 1..];
+                match name {
+                    "stdout" => parts.push((Stream::Stdout, String::new())),
+                    "stderr" => parts.push((Stream::Stderr, String::new())),
+                    _ => match transfer.variable(name) {
+                        Some(value) => text.push_str(&value),
+                        None => eprintln!("Warning: unknown --write-out variable: '{}'", name),
+                    },
+                }
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix("%header{") {
+            if let Some(end) = after.find('}') {
+                text.push_str(&transfer.header(&after[..end]));
+                rest = &after[end + 1..];
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix("%%") {
+            text.push('%');
+            rest = after;
+            continue;
+        } else if c == '\\' {
+            let escaped = match rest[1..].chars().next() {
+                Some('n') => Some('\n'),
+                Some('r') => Some('\r'),
+                Some('t') => Some('\t'),
+                _ => None,
+            };
+            if let Some(escaped) = escaped {
+                text.push(escaped);
+                rest = &rest[2..];
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    parts.retain(|(_, text)| !text.is_empty());
+    parts
+}
+
+/// Print the `-w` format for a finished request. A format starting with
+/// `@` is read from that file, or from stdin for `@-`.
+pub fn write_out(format: &str, transfer: &Transfer) -> Result<(), X402Error> {
+    let format = match format.strip_prefix('@') {
+        Some("-") => {
+            let mut format = String::new();
+            io::stdin()
+                .read_to_string(&mut format)
+                .map_err(|e| X402Error::General(format!("Failed to read stdin: {}", e)))?;
+            format
+        }
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| X402Error::General(format!("Failed to read file {}: {}", path, e)))?,
+        None => format.to_string(),
+    };
+
+    for (stream, text) in render(&format, transfer) {
+        let written = match stream {
+            Stream::Stdout => io::stdout().write_all(text.as_bytes()),
+            Stream::Stderr => io::stderr().write_all(text.as_bytes()),
+        };
+        written.map_err(|e| X402Error::General(format!("Failed to write output: {}", e)))?;
+    }
+    io::stdout()
+        .flush()
+        .map_err(|e| X402Error::General(format!("Failed to write output: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer() -> Transfer {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "application/json".parse().unwrap());
+        headers.append("x-tag", "a".parse().unwrap());
+        headers.append("x-tag", "b".parse().unwrap());
+        Transfer {
+            method: "GET".to_string(),
+            url_effective: "https://api.example.com/paid".to_string(),
+            http_code: 200,
+            http_version: "1.1",
+            headers,
+            size_download: 42,
+            time_total: Duration::from_millis(1500),
+            x402_amount: Some("0.010000".to_string()),
+            x402_network: Some("base-sepolia".to_string()),
+            x402_tx_hash: Some("0xabc".to_string()),
+        }
+    }
+
+    fn stdout(format: &str, transfer: &Transfer) -> String {
+        render(format, transfer)
+            .into_iter()
+            .filter(|(stream, _)| *stream == Stream::Stdout)
+            .map(|(_, text)| text)
+            .collect()
+    }
+
+    #[test]
+    fn test_curl_variables() {
+        assert_eq!(
+            stdout(
+                "%{http_code} %{time_total} %{size_download} %{url_effective}\\n",
+                &transfer()
+            ),
+            "200 1.500000 42 https://api.example.com/paid\n"
+        );
+        assert_eq!(
+            stdout("%{content_type};%header{x-tag}", &transfer()),
+            "application/json;a, b"
+        );
+    }
+
+    #[test]
+    fn test_x402_variables() {
+        assert_eq!(
+            stdout(
+                "%{x402_paid} %{x402_amount} %{x402_network} %{x402_tx_hash}",
+                &transfer()
+            ),
+            "1 0.010000 base-sepolia 0xabc"
+        );
+        let unpaid = Transfer {
+            http_code: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            stdout("%{x402_paid}:%{x402_amount}:%{http_code}", &unpaid),
+            "0::000"
+        );
+    }
+
+    #[test]
+    fn test_literals_and_streams() {
+        assert_eq!(
+            stdout("100%% {x} %{unknown}\\t\\q", &transfer()),
+            "100% {x} \t\\q"
+        );
+        assert_eq!(
+            render("out%{stderr}err%{stdout}more", &transfer()),
+            vec![
+                (Stream::Stdout, "out".to_string()),
+                (Stream::Stderr, "err".to_string()),
+                (Stream::Stdout, "more".to_string()),
+            ]
+        );
+    }
+}
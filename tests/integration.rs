@@ -2,6 +2,8 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use std::io::Write;
 use tempfile::NamedTempFile;
+use wiremock::matchers::{body_partial_json, body_string, header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 /// Keystore v3 JSON for Hardhat #0 key (0xac0974bec...f2ff80) with password "testpassword123"
 const TEST_KEYSTORE_JSON: &str = r#"{"address":"f39Fd6e51aad88F6F4ce6aB8827279cffFb92266","crypto":{"cipher":"aes-128-ctr","cipherparams":{"iv":"27f2444b8bfd4b13eeb89843ca857e6e"},"ciphertext":"121498fa631ea0bbaf027808fa79115692860ecae5db2a73d4cf7dd56209d045","kdf":"scrypt","kdfparams":{"dklen":32,"n":262144,"r":8,"p":1,"salt":"c6113dae558dcd445bb99c3a47c6198e"},"mac":"c29d0c74e3462191091e7ae5fcbf2b219492d9cb6e469b6abbdd540eab72710e"},"id":"6b480f64-c657-4924-928d-00256e3e6a1d","version":3}"#;
@@ -186,6 +188,70 @@ fn test_wallet_file_not_found() {
         .stderr(predicate::str::contains("not found"));
 }
 
+#[tokio::test]
+async fn test_wallet_password_file() {
+    let server = MockServer::start().await;
+    let rpc_result = |result: serde_json::Value| {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "result": result
+        }))
+    };
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({"method": "eth_chainId"}),
+        ))
+        .respond_with(rpc_result(serde_json::json!("0x14a34")))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({"method": "eth_call"})))
+        .respond_with(rpc_result(serde_json::json!("0xf4240")))
+        .mount(&server)
+        .await;
+
+    let keystore_file = write_test_keystore();
+    let mut password_file = NamedTempFile::new().unwrap();
+    password_file.write_all(b"testpassword123\n").unwrap();
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-rpc-url")
+        .arg(server.uri())
+        .arg("--x402-wallet")
+        .arg(keystore_file.path())
+        .arg("--x402-wallet-password-file")
+        .arg(password_file.path())
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .env_remove("X402_WALLET_PASSWORD")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1.000000 USDC"));
+}
+
+#[test]
+fn test_wallet_password_file_missing() {
+    let keystore_file = write_test_keystore();
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-wallet")
+        .arg(keystore_file.path())
+        .arg("--x402-wallet-password-file")
+        .arg("/nonexistent/password.txt")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .env_remove("X402_WALLET_PASSWORD")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("Failed to read password file"));
+}
+
 #[test]
 fn test_private_key_takes_priority_over_wallet() {
     // Private key should be used even when a (nonexistent) wallet is specified
@@ -265,3 +331,1725 @@ fn test_balance_custom_token() {
         .stderr(predicate::str::contains("Address:"))
         .stderr(predicate::str::contains("Network:"));
 }
+
+#[test]
+fn test_balance_unknown_network() {
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-network")
+        .arg("dogechain")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .env_remove("X402_RPC_URL")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Unknown network: dogechain"));
+}
+
+#[tokio::test]
+async fn test_balance_with_external_signer() {
+    // One mock serves both the signing daemon and the chain RPC
+    let server = MockServer::start().await;
+    let rpc_result = |result: serde_json::Value| {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "result": result
+        }))
+    };
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({"method": "eth_accounts"}),
+        ))
+        .respond_with(rpc_result(serde_json::json!([
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({"method": "eth_chainId"}),
+        ))
+        .respond_with(rpc_result(serde_json::json!("0x14a34")))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({"method": "eth_call"})))
+        .respond_with(rpc_result(serde_json::json!("0xf4240")))
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-signer")
+        .arg(server.uri())
+        .arg("--x402-rpc-url")
+        .arg(server.uri())
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Base Sepolia"))
+        .stderr(predicate::str::contains("1.000000 USDC"));
+}
+
+#[tokio::test]
+async fn test_balance_with_profile() {
+    let server = MockServer::start().await;
+    let rpc_result = |result: serde_json::Value| {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "result": result
+        }))
+    };
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({"method": "eth_chainId"}),
+        ))
+        .respond_with(rpc_result(serde_json::json!("0x14a34")))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({"method": "eth_call"})))
+        .respond_with(rpc_result(serde_json::json!("0xf4240")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join(".x402")).unwrap();
+    std::fs::write(
+        home.path().join(".x402/config"),
+        format!(
+            r#"
+private_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+
+[profiles.staging]
+private_key = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+rpc_url = "{}"
+"#,
+            server.uri()
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PROFILE", "staging")
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .env_remove("X402_RPC_URL")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_match("(?i)0x70997970c51812dc3a010c7d01b50e0d17dc79c8").unwrap())
+        .stderr(predicate::str::contains("1.000000 USDC"));
+}
+
+#[test]
+fn test_unknown_profile() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-profile")
+        .arg("missing")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("Unknown profile 'missing'"));
+}
+
+#[test]
+fn test_external_signer_invalid_url() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-signer")
+        .arg("localhost:8550")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("Invalid signer URL"));
+}
+
+const SOLANA_TEST_KEY: &str =
+    "2Ana1pUpv2ZbMVkwF5FXapYeBEjdxDatLn7nvJkhgTSdZd8hbDHTd21as7EAsg7ypityqfsw2pMQKJcVDVcAEsd";
+
+#[tokio::test]
+async fn test_balance_solana_network() {
+    let rpc = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "context": {"slot": 1},
+                "value": [{
+                    "pubkey": "9C6hybhQ6Aycep9jaUnP6uL9ZYvDjUp1aSkFWPUFJtpj",
+                    "account": {"data": {"parsed": {"info": {
+                        "mint": "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+                        "tokenAmount": {"amount": "1500000", "decimals": 6}
+                    }}}}
+                }]
+            }
+        })))
+        .expect(1)
+        .mount(&rpc)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-network")
+        .arg("solana-devnet")
+        .arg("--x402-rpc-url")
+        .arg(rpc.uri())
+        .arg("--x402-solana-key")
+        .arg(SOLANA_TEST_KEY)
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Network:  Solana Devnet\n"))
+        .stderr(predicate::str::contains(
+            "Address:  9C6hybhQ6Aycep9jaUnP6uL9ZYvDjUp1aSkFWPUFJtpj",
+        ))
+        .stderr(predicate::str::contains("USDC:     1.500000 USDC"));
+}
+
+#[test]
+fn test_balance_solana_no_credentials() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-network")
+        .arg("solana")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_SOLANA_PRIVATE_KEY")
+        .env_remove("X402_SOLANA_KEYPAIR")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("No Solana credentials found"));
+}
+
+#[test]
+fn test_all_networks_requires_balance() {
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--all-networks")
+        .arg("http://localhost:1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--x402-balance"));
+}
+
+#[test]
+fn test_all_networks_conflicts_with_network() {
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--all-networks")
+        .arg("--x402-network")
+        .arg("polygon")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+// Keyring tests
+
+#[test]
+fn test_keyring_import_list_remove() {
+    let home = tempfile::tempdir().unwrap();
+    let keys = home.path().join("keys.json");
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("x402curl").unwrap();
+        cmd.args(args)
+            .current_dir(home.path())
+            .env("HOME", home.path())
+            .env("X402_KEYRING_FILE", &keys);
+        cmd
+    };
+
+    run(&["keyring", "import", "agent-wallet"])
+        .write_stdin("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--x402-key keyring:agent-wallet"));
+
+    run(&["keyring", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("agent-wallet"))
+        .stdout(predicate::str::contains(
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        ));
+
+    run(&["keyring", "remove", "agent-wallet"])
+        .assert()
+        .success();
+    run(&["keyring", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No keys stored."));
+}
+
+#[test]
+fn test_keyring_import_rejects_invalid_key() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["keyring", "import", "agent-wallet"])
+        .env("X402_KEYRING_FILE", home.path().join("keys.json"))
+        .write_stdin("not-a-key\n")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("Invalid private key"));
+}
+
+#[test]
+fn test_keyring_reference_not_found() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-balance")
+        .arg("--x402-key")
+        .arg("keyring:missing")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_KEYRING_FILE", home.path().join("keys.json"))
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "No key named 'missing' in the keyring",
+        ));
+}
+
+// Wallet management tests
+
+#[test]
+fn test_wallet_import_address_rekey() {
+    let home = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("x402curl").unwrap();
+        cmd.args(args)
+            .current_dir(home.path())
+            .env("HOME", home.path())
+            .env_remove("X402_WALLET_PASSWORD")
+            .env_remove("X402_WALLET_NEW_PASSWORD");
+        cmd
+    };
+
+    run(&[
+        "wallet",
+        "import",
+        "agent-wallet",
+        "--password",
+        "old-password",
+    ])
+    .write_stdin("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80\n")
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("--x402-wallet agent-wallet"));
+    assert!(home.path().join(".x402/wallets/agent-wallet.json").exists());
+
+    run(&["wallet", "address", "agent-wallet"])
+        .assert()
+        .success()
+        .stdout("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266\n");
+
+    run(&[
+        "wallet",
+        "import",
+        "agent-wallet",
+        "--password",
+        "old-password",
+    ])
+    .write_stdin("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80\n")
+    .assert()
+    .failure()
+    .code(5)
+    .stderr(predicate::str::contains("already exists"));
+
+    run(&[
+        "wallet",
+        "rekey",
+        "agent-wallet",
+        "--password",
+        "old-password",
+    ])
+    .env("X402_WALLET_NEW_PASSWORD", "new-password")
+    .assert()
+    .success();
+
+    run(&[
+        "--x402-balance",
+        "--x402-wallet",
+        "agent-wallet",
+        "--x402-wallet-password",
+        "old-password",
+    ])
+    .env_remove("X402_PRIVATE_KEY")
+    .assert()
+    .failure()
+    .code(5)
+    .stderr(predicate::str::contains("Failed to decrypt keystore"));
+}
+
+#[test]
+fn test_wallet_new_requires_password() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["wallet", "new", "agent-wallet"])
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_WALLET_PASSWORD")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains("No password provided"));
+}
+
+// Config layering tests
+
+const TEST_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+#[test]
+fn test_config_show_explain() {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join(".x402")).unwrap();
+    std::fs::write(
+        home.path().join(".x402/config"),
+        "rpc_url = \"https://global.example\"\n[defaults]\nverbose = true\n",
+    )
+    .unwrap();
+    std::fs::write(
+        home.path().join(".x402.toml"),
+        "rpc_url = \"https://project.example\"\n[selection]\nnetworks = [\"base\"]\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["config", "show", "--explain"])
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .env_remove("X402_WALLET")
+        .env_remove("X402_RPC_URL")
+        .env_remove("X402_PROFILE")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "private_key = \"********\"  # environment",
+        ))
+        .stdout(predicate::str::contains(
+            "selection.networks = [\"base\"]  #",
+        ))
+        .stdout(predicate::str::contains(".x402.toml"))
+        // A project file can't redirect RPC
+        .stdout(
+            predicate::str::is_match("rpc_url = \"https://global.example\"  # .*config").unwrap(),
+        )
+        .stderr(predicate::str::contains("Warning: ignoring rpc_url from"))
+        .stdout(predicate::str::is_match("defaults.verbose = true  # .*config").unwrap())
+        .stdout(predicate::str::contains(
+            "defaults.confirm = false  # default",
+        ))
+        .stdout(predicate::str::contains("ac0974").not());
+}
+
+#[test]
+fn test_config_show_explain_command_line() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["--x402-rpc-url", "https://cli.example"])
+        .args(["config", "show", "--explain"])
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_RPC_URL", "https://env.example")
+        .env_remove("X402_PROFILE")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "rpc_url = \"https://cli.example\"  # command line",
+        ));
+}
+
+#[tokio::test]
+async fn test_config_defaults_apply_with_env_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/free"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join(".x402")).unwrap();
+    std::fs::write(
+        home.path().join(".x402/config"),
+        "[defaults]\nverbose = true\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/free", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .env_remove("X402_WALLET")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("> GET"));
+}
+
+#[tokio::test]
+async fn test_body_written_as_is_when_not_a_terminal() {
+    let server = MockServer::start().await;
+    let body = "chunk-".repeat(50_000);
+    Mock::given(method("GET"))
+        .and(path("/stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body.clone()))
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    let output = cmd
+        .arg(format!("{}/stream", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, body.as_bytes());
+
+    let out_file = home.path().join("body.txt");
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-o")
+        .arg(&out_file)
+        .arg(format!("{}/stream", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(std::fs::read_to_string(&out_file).unwrap(), body);
+}
+
+const EVENT_STREAM: &str =
+    "event: delta\ndata: {\"text\": \"Hel\"}\n\n: keep-alive\n\ndata: {\"text\": \"lo\"}\n\ndata: [DONE]\n\n";
+
+async fn mock_event_stream() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/events"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(EVENT_STREAM, "text/event-stream"))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_sse_json_lines() {
+    let server = mock_event_stream().await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-sse-json")
+        .arg(format!("{}/events", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout("{\"text\":\"Hel\"}\n{\"text\":\"lo\"}\n\"[DONE]\"\n");
+}
+
+#[tokio::test]
+async fn test_no_buffer_relays_event_stream() {
+    let server = mock_event_stream().await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-N")
+        .arg(format!("{}/events", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout(EVENT_STREAM);
+}
+
+#[tokio::test]
+async fn test_data_from_stdin() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/echo"))
+        .and(body_string("{\"from\":\"stdin\"}"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("received"))
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["-X", "POST", "-d", "@-"])
+        .arg(format!("{}/echo", server.uri()))
+        .write_stdin("{\"from\":\"stdin\"}")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout("received");
+}
+
+#[tokio::test]
+async fn test_upload_file_puts_to_directory_url() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path("/files/report.csv"))
+        .and(header("content-length", "8"))
+        .and(body_string("a,b\n1,2\n"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("stored"))
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let upload = home.path().join("report.csv");
+    std::fs::write(&upload, "a,b\n1,2\n").unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-T")
+        .arg(&upload)
+        .arg(format!("{}/files/", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout("stored");
+}
+
+#[test]
+fn test_upload_file_missing() {
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["-T", "/nonexistent/upload.bin", "http://localhost:1/"])
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Failed to read file"));
+}
+
+async fn mock_headers_endpoint() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/headers"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "abc123")
+                .set_body_string("body"),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("HEAD"))
+        .and(path("/headers"))
+        .respond_with(ResponseTemplate::new(200).insert_header("x-request-id", "abc123"))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_include_headers() {
+    let server = mock_headers_endpoint().await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-i")
+        .arg(format!("{}/headers", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("HTTP/1.1 200 OK\r\n"))
+        .stdout(predicate::str::contains("\r\nX-Request-Id: abc123\r\n"))
+        .stdout(predicate::str::ends_with("\r\n\r\nbody"));
+}
+
+#[tokio::test]
+async fn test_head_request() {
+    let server = mock_headers_endpoint().await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-I")
+        .arg(format!("{}/headers", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("HTTP/1.1 200 OK\r\n"))
+        .stdout(predicate::str::contains("X-Request-Id: abc123"))
+        .stdout(predicate::str::ends_with("\r\n\r\n"));
+}
+
+#[tokio::test]
+async fn test_dump_header_to_file() {
+    let server = mock_headers_endpoint().await;
+    let home = tempfile::tempdir().unwrap();
+    let dump = home.path().join("headers.txt");
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-D")
+        .arg(&dump)
+        .arg(format!("{}/headers", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout("body");
+    let headers = std::fs::read_to_string(&dump).unwrap();
+    assert!(headers.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(headers.contains("X-Request-Id: abc123\r\n"));
+    assert!(headers.ends_with("\r\n\r\n"));
+}
+
+#[tokio::test]
+async fn test_write_out() {
+    let server = mock_headers_endpoint().await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-w")
+        .arg(
+            "\\n%{http_code} %{size_download} %{x402_paid} [%{x402_amount}] [%{x402_network}] \
+             [%{x402_tx_hash}] %header{x-request-id}",
+        )
+        .arg(format!("{}/headers", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout("body\n200 4 0 [] [] [] abc123");
+}
+
+#[tokio::test]
+async fn test_write_out_settled_payment() {
+    let server = mock_settling_endpoint("GET").await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-w")
+        .arg("\\n%{http_code} %{x402_paid} %{x402_amount} %{x402_network} %{x402_tx_hash}")
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-rpc-url")
+        .arg(format!("{}/rpc", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .success()
+        .stdout(format!(
+            "paid content\n200 1 0.010000 base-sepolia {}",
+            SETTLEMENT_TX
+        ));
+}
+
+#[tokio::test]
+async fn test_write_out_on_http_failure() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["-f", "-w", "%{http_code}"])
+        .arg(format!("{}/missing", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .failure()
+        .code(4)
+        .stdout("404");
+}
+
+#[tokio::test]
+async fn test_write_out_on_connection_failure() {
+    // Nothing listens on the port once the listener is dropped
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let url = format!("http://127.0.0.1:{}", port);
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args([
+        "-w",
+        "%{http_code} %{x402_paid} [%{x402_amount}] [%{x402_tx_hash}]",
+    ])
+    .arg(format!("{}/paid", url))
+    .current_dir(home.path())
+    .env("HOME", home.path())
+    .env("X402_PRIVATE_KEY", TEST_KEY)
+    .assert()
+    .failure()
+    .code(2)
+    .stdout("000 0 [] []");
+}
+
+#[tokio::test]
+async fn test_write_out_on_refused_payment() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    write_policy(home.path(), "allow_hosts = [\"*.example.com\"]");
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["-w", "%{http_code} %{x402_paid} %{url_effective}"])
+        .arg(format!("{}/paid", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env("X402_PRIVATE_KEY", TEST_KEY)
+        .assert()
+        .failure()
+        .code(3)
+        .stdout(format!("402 0 {}/paid", server.uri()));
+}
+
+// Spending limit tests
+
+fn payment_required_v1(amount: &str) -> serde_json::Value {
+    serde_json::json!({
+        "x402Version": 1,
+        "error": "X-PAYMENT header is required",
+        "accepts": [{
+            "scheme": "exact",
+            "network": "base-sepolia",
+            "maxAmountRequired": amount,
+            "resource": "http://localhost/paid",
+            "description": "Paid endpoint",
+            "mimeType": "application/json",
+            "payTo": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+            "maxTimeoutSeconds": 60,
+            "asset": "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+            "extra": {"name": "USDC", "version": "2"}
+        }]
+    })
+}
+
+async fn mock_paid_endpoint(amount: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(payment_required_v1(amount)))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Settlement transaction hash returned by [`mock_settling_endpoint`].
+const SETTLEMENT_TX: &str = "0x5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e5a1e";
+
+/// Mock server for a payment that goes through: `/paid` answers 402 until the
+/// request carries an X-PAYMENT header, then 200 with a settlement header,
+/// and `/rpc` reports a balance of 1 USDC.
+async fn mock_settling_endpoint(http_method: &str) -> MockServer {
+    use base64::Engine;
+
+    let server = MockServer::start().await;
+    let settlement = serde_json::json!({
+        "success": true,
+        "transaction": SETTLEMENT_TX,
+        "network": "base-sepolia",
+        "payer": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+    });
+    Mock::given(method(http_method))
+        .and(path("/paid"))
+        .and(header_exists("X-PAYMENT"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "x-payment-response",
+                    base64::engine::general_purpose::STANDARD.encode(settlement.to_string()),
+                )
+                .set_body_string("paid content"),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method(http_method))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(payment_required_v1("10000")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_rpc(
+        &server,
+        "0x00000000000000000000000000000000000000000000000000000000000f4240",
+    )
+    .await;
+    server
+}
+
+/// Mount a Base Sepolia RPC at `/rpc` that answers every `eth_call` with
+/// `balance`.
+async fn mount_rpc(server: &MockServer, balance: &str) {
+    let rpc_result = |result: &str| {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "result": result
+        }))
+    };
+    Mock::given(method("POST"))
+        .and(path("/rpc"))
+        .and(body_partial_json(
+            serde_json::json!({"method": "eth_chainId"}),
+        ))
+        .respond_with(rpc_result("0x14a34"))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/rpc"))
+        .and(body_partial_json(serde_json::json!({"method": "eth_call"})))
+        .respond_with(rpc_result(balance))
+        .mount(server)
+        .await;
+}
+
+/// The JSON payload of the X-PAYMENT header of the paid retry.
+async fn sent_payment(server: &MockServer) -> serde_json::Value {
+    use base64::Engine;
+
+    let requests = server.received_requests().await.unwrap();
+    let header = requests
+        .iter()
+        .find_map(|r| r.headers.get("X-PAYMENT"))
+        .expect("no paid retry");
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(header.as_bytes())
+        .unwrap();
+    serde_json::from_slice(&decoded).unwrap()
+}
+
+#[tokio::test]
+async fn test_paid_request_through_external_signer() {
+    // Any well-formed signature will do: the mock server doesn't verify it
+    let signature = format!("0x{}{}1b", "11".repeat(32), "22".repeat(32));
+    let signer = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({"method": "eth_accounts"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "result": ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"]
+        })))
+        .mount(&signer)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "account_signTypedData",
+            "params": ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", {
+                "primaryType": "TransferWithAuthorization",
+                "domain": {"name": "USDC", "version": "2"},
+                "message": {
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+                    "value": "10000"
+                }
+            }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "result": signature
+        })))
+        .expect(1)
+        .mount(&signer)
+        .await;
+    let server = mock_settling_endpoint("GET").await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-signer")
+        .arg(signer.uri())
+        .arg("--x402-rpc-url")
+        .arg(format!("{}/rpc", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env_remove("X402_PRIVATE_KEY")
+        .env_remove("X402_WALLET")
+        .assert()
+        .success()
+        .stdout("paid content");
+
+    let payment = sent_payment(&server).await;
+    assert_eq!(payment["payload"]["signature"], signature);
+    assert_eq!(
+        payment["payload"]["authorization"]["from"]
+            .as_str()
+            .unwrap()
+            .to_lowercase(),
+        "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+    );
+}
+
+#[tokio::test]
+async fn test_paid_request_records_ledger_report_and_receipt() {
+    let server = mock_settling_endpoint("GET").await;
+    let home = tempfile::tempdir().unwrap();
+    let receipt_path = home.path().join("receipt.json");
+
+    let output = Command::cargo_bin("x402curl")
+        .unwrap()
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-json")
+        .arg("--x402-receipt")
+        .arg(&receipt_path)
+        .arg("--x402-rpc-url")
+        .arg(format!("{}/rpc", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"paid content");
+
+    // JSON report
+    let report = stderr_json(&output);
+    assert_eq!(report["success"], true);
+    assert_eq!(report["status"], 200);
+    let payment = &report["payment"];
+    assert_eq!(payment["url"], format!("{}/paid", server.uri()));
+    assert_eq!(payment["method"], "GET");
+    assert_eq!(payment["network"], "base-sepolia");
+    assert_eq!(payment["amount"], "10000");
+    assert_eq!(
+        payment["pay_to"],
+        "0x209693Bc6afc0C5328bA36FaF03C514EF312287C"
+    );
+    assert_eq!(payment["transaction"], SETTLEMENT_TX);
+    assert_eq!(report["settlement"]["success"], true);
+    assert_eq!(report["settlement"]["transaction"], SETTLEMENT_TX);
+    assert_eq!(report["settlement"]["network"], "base-sepolia");
+
+    // Ledger
+    let ledger = std::fs::read_to_string(home.path().join(".x402").join("ledger.jsonl")).unwrap();
+    let lines: Vec<&str> = ledger.lines().collect();
+    assert_eq!(lines.len(), 1);
+    let entry: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(&entry, payment);
+
+    // Receipt
+    let receipt: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&receipt_path).unwrap()).unwrap();
+    assert_eq!(&receipt["payment"], payment);
+    assert_eq!(receipt["settlement"], report["settlement"]);
+}
+
+#[tokio::test]
+async fn test_max_amount_flag_rejects_payment() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-max-amount")
+        .arg("0.001")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("exceeds maximum amount 0.001"));
+}
+
+#[tokio::test]
+async fn test_undecodable_requirements_refused_with_limits() {
+    let mut required = payment_required_v1("10000");
+    required["accepts"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("payTo");
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(required))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-max-amount")
+        .arg("1")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "cannot decode the payment requirements",
+        ));
+}
+
+#[tokio::test]
+async fn test_max_amount_from_config_file() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join(".x402")).unwrap();
+    std::fs::write(
+        home.path().join(".x402").join("config"),
+        "[limits]\nmax_amount = \"0.005\"\n",
+    )
+    .unwrap();
+
+    // Limits apply even when the key comes from the environment
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .env(
+            "X402_PRIVATE_KEY",
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("exceeds maximum amount 0.005"));
+}
+
+#[test]
+fn test_max_amount_invalid() {
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["--x402-max-amount", "ten", "https://example.com"])
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Invalid token amount"));
+}
+
+#[tokio::test]
+async fn test_confirm_declined_makes_one_request() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--confirm")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Proceed? [y/N]"))
+        .stderr(predicate::str::contains("Payment cancelled."));
+}
+
+#[tokio::test]
+async fn test_confirm_sends_full_request_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/paid"))
+        .and(wiremock::matchers::body_string("{\"q\":1}"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(payment_required_v1("10000")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["-X", "POST", "-d", "{\"q\":1}", "--confirm"])
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Payment cancelled."));
+}
+
+/// Without a terminal, --confirm can't ask for an answer once stdin has been
+/// read as the body. `setsid` runs x402curl without a controlling
+/// terminal.
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_confirm_without_terminal_when_body_from_stdin() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(payment_required_v1("10000")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut setsid = std::process::Command::new("setsid");
+    setsid
+        .arg("--wait")
+        .arg(assert_cmd::cargo::cargo_bin("x402curl"));
+    Command::from_std(setsid)
+        .args(["-X", "POST", "-d", "@-", "--confirm"])
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "--confirm needs a terminal when stdin is already in use",
+        ));
+}
+
+#[tokio::test]
+async fn test_confirm_accepted_retries_once_with_full_body() {
+    let server = mock_settling_endpoint("POST").await;
+    let body = "{\"prompt\":\"hello\",\"max_tokens\":64}";
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["-X", "POST", "-d", body, "--confirm"])
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-rpc-url")
+        .arg(format!("{}/rpc", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Proceed? [y/N]"))
+        .stdout("paid content");
+
+    // One unpaid request and exactly one paid retry, both with the full body
+    let requests: Vec<_> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|r| r.url.path() == "/paid")
+        .collect();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[0].headers.contains_key("X-PAYMENT"));
+    assert!(requests[1].headers.contains_key("X-PAYMENT"));
+    for request in &requests {
+        assert_eq!(String::from_utf8_lossy(&request.body), body);
+    }
+}
+
+/// Write an executable shell script into `dir`.
+#[cfg(unix)]
+fn write_script(dir: &std::path::Path, name: &str, body: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_approve_cmd_declines() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let seen = home.path().join("requirements.json");
+    let script = write_script(home.path(), "approve.sh", "cat > \"$1\"; exit 1");
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-approve-cmd")
+        .arg(format!("{} {}", script.display(), seen.display()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("Payment required (x402 v1)"))
+        .stderr(predicate::str::contains(
+            "payment declined by approval command",
+        ));
+
+    let requirements: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(seen).unwrap()).unwrap();
+    assert_eq!(requirements["url"], format!("{}/paid", server.uri()));
+    assert_eq!(requirements["accepts"][0]["network"], "base-sepolia");
+    assert_eq!(requirements["accepts"][0]["amount"], "10000");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_approve_cmd_from_config() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let script = write_script(home.path(), "decline.sh", "echo no thanks; exit 1");
+    std::fs::create_dir(home.path().join(".x402")).unwrap();
+    std::fs::write(
+        home.path().join(".x402").join("config"),
+        format!("approve_cmd = {:?}\n", script.display().to_string()),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("no thanks"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_approve_cmd_output_kept_out_of_json_report() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let script = write_script(
+        home.path(),
+        "decline.sh",
+        "echo no thanks; echo sorry >&2; exit 1",
+    );
+    let output = Command::cargo_bin("x402curl")
+        .unwrap()
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-json")
+        .arg("--x402-approve-cmd")
+        .arg(script.display().to_string())
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let report = stderr_json(&output);
+    assert!(report["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("payment declined by approval command"));
+}
+
+fn write_policy(home: &std::path::Path, policy: &str) {
+    std::fs::create_dir(home.join(".x402")).unwrap();
+    std::fs::write(
+        home.join(".x402").join("config"),
+        format!("[policy]\n{}\n", policy),
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_policy_denied_host_shows_402() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    write_policy(home.path(), "allow_hosts = [\"*.example.com\"]");
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains("Payment required (x402 v1)"))
+        .stderr(predicate::str::contains(
+            "payment denied by policy: host 127.0.0.1 is not allowed",
+        ));
+
+    let log = std::fs::read_to_string(home.path().join(".x402").join("policy.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 1);
+    assert!(log.contains(&format!("{}/paid", server.uri())));
+    assert!(log.contains("host 127.0.0.1 is not allowed"));
+}
+
+#[tokio::test]
+async fn test_policy_checks_host_after_redirect() {
+    let server = mock_paid_endpoint("10000").await;
+    let port = server.address().port();
+    // An allowed host redirecting to one that isn't
+    Mock::given(method("GET"))
+        .and(path("/start"))
+        .respond_with(
+            ResponseTemplate::new(302)
+                .insert_header("location", format!("http://127.0.0.1:{}/paid", port)),
+        )
+        .mount(&server)
+        .await;
+    let home = tempfile::tempdir().unwrap();
+    write_policy(home.path(), "allow_hosts = [\"localhost\"]");
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-L")
+        .arg(format!("http://localhost:{}/start", port))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "payment denied by policy: host 127.0.0.1 is not allowed",
+        ));
+}
+
+#[tokio::test]
+async fn test_policy_denied_recipient_json() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    write_policy(
+        home.path(),
+        "deny_pay_to = [\"0x209693bc6afc0c5328ba36faf03c514ef312287c\"]",
+    );
+
+    let output = Command::cargo_bin("x402curl")
+        .unwrap()
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-json")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let report = stderr_json(&output);
+    assert_eq!(report["status"], 402);
+    assert_eq!(
+        report["payment_required"]["accepts"][0]["pay_to"],
+        "0x209693Bc6afc0C5328bA36FaF03C514EF312287C"
+    );
+    assert!(report["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("recipient"));
+}
+
+#[tokio::test]
+async fn test_insufficient_funds_rejected_before_signing() {
+    let server = mock_paid_endpoint("10000").await;
+    // balanceOf returns 2500 atomic units (0.0025 USDC)
+    mount_rpc(
+        &server,
+        "0x00000000000000000000000000000000000000000000000000000000000009c4",
+    )
+    .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-rpc-url")
+        .arg(format!("{}/rpc", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "insufficient funds: need 0.010000 USDC on base-sepolia, have 0.002500 USDC",
+        ));
+}
+
+#[tokio::test]
+async fn test_empty_balance_result_is_unknown() {
+    let server = mock_paid_endpoint("10000").await;
+    // An address without a contract answers eth_call with no data
+    mount_rpc(&server, "0x").await;
+
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg(format!("{}/paid", server.uri()))
+        .arg("--x402-rpc-url")
+        .arg(format!("{}/rpc", server.uri()))
+        .arg("--x402-funded-only")
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "no payment option on a network where the wallet balance could be verified",
+        ));
+}
+
+// Ledger tests
+
+#[test]
+fn test_ledger_empty() {
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-ledger")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No payments recorded."));
+}
+
+#[test]
+fn test_ledger_filter_and_total() {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join(".x402")).unwrap();
+    let entry = |timestamp: &str, url: &str, amount: &str| {
+        serde_json::json!({
+            "timestamp": timestamp,
+            "url": url,
+            "method": "POST",
+            "scheme": "exact",
+            "network": "base-sepolia",
+            "asset": "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+            "amount": amount,
+            "pay_to": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+            "payer": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "status": 200,
+            "transaction": "0xabc123"
+        })
+        .to_string()
+    };
+    let ledger = [
+        entry(
+            "2026-09-30T10:00:00Z",
+            "https://ocr.example.com/ocr",
+            "10000",
+        ),
+        entry(
+            "2026-10-01T10:00:00Z",
+            "https://ocr.example.com/ocr",
+            "20000",
+        ),
+        entry(
+            "2026-10-02T10:00:00Z",
+            "https://tts.example.com/speak",
+            "50000",
+        ),
+    ]
+    .join("\n");
+    std::fs::write(home.path().join(".x402").join("ledger.jsonl"), ledger).unwrap();
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["--x402-ledger", "--x402-ledger-host", "ocr.example.com"])
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0xabc123"))
+        .stdout(predicate::str::contains("tts.example.com").not())
+        .stdout(predicate::str::contains(
+            "Total:    0.030000 USDC on base-sepolia (2 payments)",
+        ));
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.args(["--x402-ledger", "--x402-ledger-since", "2026-10-01"])
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Total:    0.070000 USDC on base-sepolia (2 payments)",
+        ));
+}
+
+// Budget tests
+
+#[tokio::test]
+async fn test_daily_budget_counts_ledger_spend() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let x402_dir = home.path().join(".x402");
+    std::fs::create_dir(&x402_dir).unwrap();
+    std::fs::write(x402_dir.join("config"), "[budget]\ndaily = \"0.015\"\n").unwrap();
+
+    let earlier = serde_json::json!({
+        "timestamp": (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339(),
+        "url": format!("{}/paid", server.uri()),
+        "method": "GET",
+        "scheme": "exact",
+        "network": "base-sepolia",
+        "asset": "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+        "amount": "10000",
+        "pay_to": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+        "payer": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "status": 200,
+        "transaction": null
+    });
+    std::fs::write(x402_dir.join("ledger.jsonl"), earlier.to_string()).unwrap();
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("-v")
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "* daily budget: 0.005 of 0.015 remaining",
+        ))
+        .stderr(predicate::str::contains("would exceed the daily budget"));
+}
+
+// Payment requirements display tests
+
+#[tokio::test]
+async fn test_dry_run_shows_v1_requirements() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(payment_required_v1("10000")))
+        .mount(&server)
+        .await;
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-dry-run")
+        .arg(format!("{}/paid", server.uri()))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Payment required (x402 v1):"))
+        .stderr(predicate::str::contains(
+            "[1] 0.010000 USDC on Base Sepolia (base-sepolia) \
+             to 0x209693Bc6afc0C5328bA36FaF03C514EF312287C (exact)",
+        ))
+        .stderr(predicate::str::contains("(dry run - no payment made)"));
+}
+
+#[tokio::test]
+async fn test_dry_run_shows_v2_requirements() {
+    use base64::Engine;
+
+    let required = serde_json::json!({
+        "x402Version": 2,
+        "resource": {
+            "url": "http://localhost/paid",
+            "description": "Paid endpoint",
+            "mimeType": "application/json"
+        },
+        "accepts": [{
+            "scheme": "exact",
+            "network": "eip155:8453",
+            "amount": "2500000",
+            "payTo": "0x209693Bc6afc0C5328bA36FaF03C514EF312287C",
+            "maxTimeoutSeconds": 60,
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+        }]
+    });
+    let header = base64::engine::general_purpose::STANDARD.encode(required.to_string());
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).insert_header("PAYMENT-REQUIRED", header))
+        .mount(&server)
+        .await;
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    cmd.arg("--x402-dry-run")
+        .arg(format!("{}/paid", server.uri()))
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Payment required (x402 v2):"))
+        .stderr(predicate::str::contains(
+            "[1] 2.500000 USDC on Base (eip155:8453)",
+        ));
+}
+
+// JSON output tests
+
+fn stderr_json(output: &std::process::Output) -> serde_json::Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    serde_json::from_str(stderr.trim()).expect("stderr should be a single JSON document")
+}
+
+#[tokio::test]
+async fn test_json_dry_run_requirements() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/paid"))
+        .respond_with(ResponseTemplate::new(402).set_body_json(payment_required_v1("10000")))
+        .mount(&server)
+        .await;
+
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    let output = cmd
+        .args(["--x402-dry-run", "--x402-json"])
+        .arg(format!("{}/paid", server.uri()))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json = stderr_json(&output);
+    assert_eq!(json["success"], true);
+    assert_eq!(json["status"], 402);
+    assert_eq!(json["payment_required"]["x402_version"], 1);
+    let option = &json["payment_required"]["accepts"][0];
+    assert_eq!(option["amount"], "10000");
+    assert_eq!(option["amount_formatted"], "0.010000");
+    assert_eq!(option["symbol"], "USDC");
+    assert_eq!(option["network"], "base-sepolia");
+    assert_eq!(
+        option["pay_to"],
+        "0x209693Bc6afc0C5328bA36FaF03C514EF312287C"
+    );
+}
+
+#[tokio::test]
+async fn test_json_error_report() {
+    let server = mock_paid_endpoint("10000").await;
+    let home = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin("x402curl").unwrap();
+    let output = cmd
+        .args(["--x402-json", "--x402-max-amount", "0.001"])
+        .arg(format!("{}/paid", server.uri()))
+        .arg("--x402-key")
+        .arg("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        .current_dir(home.path())
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+
+    let json = stderr_json(&output);
+    assert_eq!(json["success"], false);
+    assert_eq!(json["error"]["code"], 3);
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("exceeds maximum amount"));
+}